@@ -5,7 +5,7 @@ use inputbot::{
 };
 use std::thread;
 // This example demonstrates blocking input with conditional flags, such as another key being
-// pressed or toggled. This example currently does not work on Linux.
+// pressed or toggled. Blocking mouse buttons currently does not work on Linux.
 
 fn main() {
     // Block the A key when left shift is held. Note: callbacks for blockable binds won't be
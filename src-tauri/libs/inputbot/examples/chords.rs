@@ -0,0 +1,15 @@
+use inputbot::{KeybdChord, KeybdKey::*, Modifier::*};
+
+// This example demonstrates binding key combinations. A chord only fires when exactly its
+// modifiers are held, so Control+Shift+Space below won't trigger the Control+Space bind.
+
+fn main() {
+    KeybdChord::new(&[Control], SpaceKey).bind(|| println!("Control+Space"));
+    KeybdChord::new(&[Control, Shift], SpaceKey).bind(|| println!("Control+Shift+Space"));
+
+    // Either the left or the right key of a modifier may be held.
+    KeybdChord::new(&[Alt], F9Key).bind(|| println!("Alt+F9"));
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+}
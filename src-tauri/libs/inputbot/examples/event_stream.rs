@@ -0,0 +1,20 @@
+// This example demonstrates receiving every input event through a channel instead of binding
+// individual keys.
+
+fn main() {
+    let events = inputbot::subscribe_events();
+
+    // Events are delivered while handle_input_events is running, so run it on its own thread.
+    std::thread::spawn(|| inputbot::handle_input_events(false));
+
+    for event in events {
+        let device = event.device.map(|device| device.name).unwrap_or_default();
+        println!(
+            "{:>10.3}s {:?} {:?} {}",
+            event.time.as_secs_f64(),
+            event.modifiers.iter().collect::<Vec<_>>(),
+            event.kind,
+            device
+        );
+    }
+}
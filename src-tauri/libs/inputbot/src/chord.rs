@@ -0,0 +1,357 @@
+use crate::{common::*, public::*};
+
+#[cfg(feature = "serde")]
+use serde::{
+    de::{Deserializer, Error},
+    Deserialize, Serialize, Serializer,
+};
+
+/// A modifier which can be held as part of a `KeybdChord`. The left and right keys of a
+/// modifier are treated as the same modifier.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum Modifier {
+    Control,
+    Alt,
+    Shift,
+    Super,
+}
+
+impl Modifier {
+    /// All modifiers in the order they appear in a chord's canonical name.
+    pub const ALL: [Modifier; 4] = [
+        Modifier::Control,
+        Modifier::Alt,
+        Modifier::Shift,
+        Modifier::Super,
+    ];
+
+    /// Returns the left and right keys of the modifier.
+    pub fn keys(self) -> [KeybdKey; 2] {
+        match self {
+            Modifier::Control => [KeybdKey::LControlKey, KeybdKey::RControlKey],
+            Modifier::Alt => [KeybdKey::LAltKey, KeybdKey::RAltKey],
+            Modifier::Shift => [KeybdKey::LShiftKey, KeybdKey::RShiftKey],
+            Modifier::Super => [KeybdKey::LSuper, KeybdKey::RSuper],
+        }
+    }
+
+    /// Returns the modifier a given key belongs to, if any.
+    pub fn from_key(key: KeybdKey) -> Option<Modifier> {
+        Modifier::ALL
+            .into_iter()
+            .find(|modifier| modifier.keys().contains(&key))
+    }
+
+    /// Returns true if either key of the modifier is currently pressed.
+    pub fn is_pressed(self) -> bool {
+        self.keys().into_iter().any(KeybdKey::is_pressed)
+    }
+
+    pub fn canonical_name(self) -> &'static str {
+        match self {
+            Modifier::Control => "Control",
+            Modifier::Alt => "Alt",
+            Modifier::Shift => "Shift",
+            Modifier::Super => "Super",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        match self {
+            Modifier::Control => 1,
+            Modifier::Alt => 1 << 1,
+            Modifier::Shift => 1 << 2,
+            Modifier::Super => 1 << 3,
+        }
+    }
+}
+
+impl std::fmt::Display for Modifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.canonical_name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::str::FromStr for Modifier {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "control" | "ctrl" => Ok(Modifier::Control),
+            "alt" | "option" => Ok(Modifier::Alt),
+            "shift" => Ok(Modifier::Shift),
+            "super" | "windows" | "win" | "command" | "cmd" | "meta" => Ok(Modifier::Super),
+            _ => Err(ParseError::UnknownFormat { val: s.to_string() }),
+        }
+    }
+}
+
+/// A set of `Modifier`s.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+
+    /// Returns the set of modifiers which are currently pressed.
+    pub fn pressed() -> Modifiers {
+        Modifier::ALL
+            .into_iter()
+            .filter(|modifier| modifier.is_pressed())
+            .collect()
+    }
+
+    pub fn contains(self, modifier: Modifier) -> bool {
+        self.0 & modifier.bit() != 0
+    }
+
+    pub fn insert(&mut self, modifier: Modifier) {
+        self.0 |= modifier.bit();
+    }
+
+    pub fn remove(&mut self, modifier: Modifier) {
+        self.0 &= !modifier.bit();
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterates over the modifiers in the set in canonical order.
+    pub fn iter(self) -> impl Iterator<Item = Modifier> {
+        Modifier::ALL
+            .into_iter()
+            .filter(move |modifier| self.contains(*modifier))
+    }
+}
+
+impl FromIterator<Modifier> for Modifiers {
+    fn from_iter<I: IntoIterator<Item = Modifier>>(iter: I) -> Self {
+        let mut modifiers = Modifiers::NONE;
+        for modifier in iter {
+            modifiers.insert(modifier);
+        }
+        modifiers
+    }
+}
+
+/// A key pressed together with a set of modifiers, e.g. `Control+Alt+Space`.
+///
+/// A chord bind only fires when exactly its modifiers are held: `Control+Space` does not fire
+/// for `Control+Shift+Space`.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct KeybdChord {
+    pub modifiers: Modifiers,
+    pub key: KeybdKey,
+}
+
+impl KeybdChord {
+    pub fn new(modifiers: &[Modifier], key: KeybdKey) -> Self {
+        let mut modifiers: Modifiers = modifiers.iter().copied().collect();
+        // The key itself is not counted as a held modifier
+        if let Some(modifier) = Modifier::from_key(key) {
+            modifiers.remove(modifier);
+        }
+        Self { modifiers, key }
+    }
+
+    /// Returns the chord made of a given key and the modifiers which are currently pressed.
+    pub fn pressed_with(key: KeybdKey) -> Self {
+        let mut modifiers = Modifiers::pressed();
+        if let Some(modifier) = Modifier::from_key(key) {
+            modifiers.remove(modifier);
+        }
+        Self { modifiers, key }
+    }
+
+    pub fn bind<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        KEYBD_CHORD_BINDS
+            .lock()
+            .unwrap()
+            .insert(self, Bind::Normal(Arc::new(callback)));
+    }
+
+    /// Binds a callback to the release of the chord's key. The modifiers are matched when the
+    /// key is pressed, so they may be released before the key.
+    pub fn bind_release<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        KEYBD_CHORD_RELEASE_BINDS
+            .lock()
+            .unwrap()
+            .insert(self, Bind::Release(Arc::new(callback)));
+    }
+
+    pub fn block_bind<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        KEYBD_CHORD_BINDS
+            .lock()
+            .unwrap()
+            .insert(self, Bind::Block(Arc::new(callback)));
+    }
+
+    pub fn blockable_bind<F: Fn() -> BlockInput + Send + Sync + 'static>(self, callback: F) {
+        KEYBD_CHORD_BINDS
+            .lock()
+            .unwrap()
+            .insert(self, Bind::Blockable(Arc::new(callback)));
+    }
+
+    pub fn is_bound(self) -> bool {
+        KEYBD_CHORD_BINDS.lock().unwrap().contains_key(&self)
+    }
+
+    pub fn unbind(self) {
+        KEYBD_CHORD_BINDS.lock().unwrap().remove(&self);
+    }
+
+    pub fn unbind_release(self) {
+        KEYBD_CHORD_RELEASE_BINDS.lock().unwrap().remove(&self);
+    }
+
+    // the canonical_name is guaranteed to roundtrip to and from the serialization format.
+    pub fn canonical_name(self) -> String {
+        self.modifiers
+            .iter()
+            .map(|modifier| modifier.canonical_name().to_owned())
+            .chain(std::iter::once(self.key.canonical_name()))
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+}
+
+impl From<KeybdKey> for KeybdChord {
+    fn from(key: KeybdKey) -> Self {
+        KeybdChord::new(&[], key)
+    }
+}
+
+impl std::fmt::Display for KeybdChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.canonical_name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::str::FromStr for KeybdChord {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+        if key_name.is_empty() {
+            return Err(ParseError::UnknownFormat { val: s.to_string() });
+        }
+
+        let mut modifiers = Vec::with_capacity(parts.len());
+        for part in parts {
+            let modifier = match Modifier::from_str(part) {
+                Ok(modifier) => modifier,
+                // Side-specific names such as "LeftControl" are accepted as modifiers too
+                Err(err) => KeybdKey::from_str(part)
+                    .ok()
+                    .and_then(Modifier::from_key)
+                    .ok_or(err)?,
+            };
+            modifiers.push(modifier);
+        }
+
+        let key = match KeybdKey::from_str(key_name) {
+            Ok(key) => key,
+            // A chord of modifiers only, e.g. "Control+Alt", ends with the left modifier key
+            Err(err) => Modifier::from_str(key_name)
+                .map(|modifier| modifier.keys()[0])
+                .map_err(|_| err)?,
+        };
+
+        Ok(KeybdChord::new(&modifiers, key))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for KeybdChord {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.canonical_name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for KeybdChord {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        std::str::FromStr::from_str(&s).map_err(Error::custom)
+    }
+}
+
+/// Runs the chord bind matching a pressed key and the modifiers currently held.
+/// Returns true if the key press should be blocked.
+pub(crate) fn handle_chord_press(key: KeybdKey) -> bool {
+    let chord = KeybdChord::pressed_with(key);
+
+    if KEYBD_CHORD_RELEASE_BINDS
+        .lock()
+        .unwrap()
+        .contains_key(&chord)
+    {
+        ACTIVE_CHORDS.lock().unwrap().insert(key, chord);
+    }
+
+    run_press_bind(&KEYBD_CHORD_BINDS, &chord)
+}
+
+/// Runs the release bind of the chord which was matched when a key was pressed.
+pub(crate) fn handle_chord_release(key: KeybdKey) {
+    let Some(chord) = ACTIVE_CHORDS.lock().unwrap().remove(&key) else {
+        return;
+    };
+    if let Some(Bind::Release(cb)) = KEYBD_CHORD_RELEASE_BINDS.lock().unwrap().get(&chord) {
+        run_callback(cb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "serde")]
+    fn chord_canonical_name_roundtrips() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{KeybdChord, KeybdKey, Modifier};
+        use std::str::FromStr;
+
+        use strum::IntoEnumIterator;
+
+        for k in KeybdKey::iter() {
+            let chord = KeybdChord::new(&[Modifier::Control, Modifier::Alt], k);
+            assert!(KeybdChord::from_str(&chord.canonical_name())? == chord);
+        }
+
+        let chord = KeybdChord::new(&Modifier::ALL, KeybdKey::OtherKey(42));
+        assert!(KeybdChord::from_str(&chord.canonical_name())? == chord);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn chord_parsing() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{KeybdChord, KeybdKey, Modifier};
+        use std::str::FromStr;
+
+        let expected = KeybdChord::new(&[Modifier::Control, Modifier::Alt], KeybdKey::SpaceKey);
+        assert!(KeybdChord::from_str("Ctrl+Alt+Space")? == expected);
+        assert!(KeybdChord::from_str("alt + control + space")? == expected);
+        assert!(KeybdChord::from_str("LeftControl+RightAlt+Space")? == expected);
+        assert!(expected.canonical_name() == "Control+Alt+Space");
+
+        assert!(KeybdChord::from_str("F9")? == KeybdChord::from(KeybdKey::F9Key));
+        assert!(
+            KeybdChord::from_str("Ctrl+Shift")?
+                == KeybdChord::new(&[Modifier::Control], KeybdKey::LShiftKey)
+        );
+
+        assert!(KeybdChord::from_str("").is_err());
+        assert!(KeybdChord::from_str("Ctrl+").is_err());
+        assert!(KeybdChord::from_str("Hyper+A").is_err());
+        Ok(())
+    }
+}
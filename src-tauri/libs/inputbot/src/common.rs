@@ -1,15 +1,24 @@
-use crate::public::*;
+use crate::{chord::*, event::*, public::*};
 use once_cell::sync::Lazy;
 pub use std::{
     collections::hash_map::HashMap,
+    hash::Hash,
+    panic::{catch_unwind, AssertUnwindSafe},
     sync::atomic::Ordering,
-    sync::{atomic::AtomicBool, Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
     thread::spawn,
+    time::Duration,
 };
 
+// How long an idle callback worker waits for another callback before its thread exits
+const CALLBACK_WORKER_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub enum Bind {
     Normal(Handler),
-    #[cfg(target_os = "windows")]
     Release(Handler),
     Block(BlockHandler),
     Blockable(BlockableHandler),
@@ -20,6 +29,7 @@ pub type BlockHandler = Arc<dyn Fn() + Send + Sync + 'static>;
 pub type BlockableHandler = Arc<dyn Fn() -> BlockInput + Send + Sync + 'static>;
 pub type KeybdBindMap = HashMap<KeybdKey, Bind>;
 pub type MouseBindMap = HashMap<MouseButton, Bind>;
+pub type KeybdChordBindMap = HashMap<KeybdChord, Bind>;
 
 pub static HANDLE_EVENTS: AtomicBool = AtomicBool::new(false);
 pub static KEYBD_BINDS: Lazy<Mutex<KeybdBindMap>> = Lazy::new(|| Mutex::new(KeybdBindMap::new()));
@@ -28,12 +38,111 @@ pub static KEYBD_RELEASE_BINDS: Lazy<Mutex<KeybdBindMap>> =
 pub static MOUSE_BINDS: Lazy<Mutex<MouseBindMap>> = Lazy::new(|| Mutex::new(MouseBindMap::new()));
 pub static MOUSE_RELEASE_BINDS: Lazy<Mutex<MouseBindMap>> =
     Lazy::new(|| Mutex::new(MouseBindMap::new()));
+pub static KEYBD_CHORD_BINDS: Lazy<Mutex<KeybdChordBindMap>> =
+    Lazy::new(|| Mutex::new(KeybdChordBindMap::new()));
+pub static KEYBD_CHORD_RELEASE_BINDS: Lazy<Mutex<KeybdChordBindMap>> =
+    Lazy::new(|| Mutex::new(KeybdChordBindMap::new()));
+// Chords matched on key press, so that release binds fire even if the modifiers are released first
+pub static ACTIVE_CHORDS: Lazy<Mutex<HashMap<KeybdKey, KeybdChord>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+pub static EVENT_SUBSCRIBERS: Lazy<Mutex<Vec<EventSubscriber>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+static CALLBACK_POOL: Lazy<CallbackPool> = Lazy::new(CallbackPool::new);
+
+/// Threads running bound callbacks. Idle threads are reused, and a new one is started when all of
+/// them are busy, so that long-running callbacks never delay other binds.
+struct CallbackPool {
+    sender: Mutex<Sender<Handler>>,
+    receiver: Arc<Mutex<Receiver<Handler>>>,
+    // Workers waiting for a callback which no queued callback has been promised to yet
+    idle: Arc<AtomicUsize>,
+}
+
+impl CallbackPool {
+    fn new() -> Self {
+        let (sender, receiver) = channel::<Handler>();
+        CallbackPool {
+            sender: Mutex::new(sender),
+            receiver: Arc::new(Mutex::new(receiver)),
+            idle: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn run(&self, cb: Handler) {
+        if take_one(&self.idle).is_err() {
+            self.spawn_worker();
+        }
+        let _ = self.sender.lock().unwrap().send(cb);
+    }
+
+    fn spawn_worker(&self) {
+        let receiver = Arc::clone(&self.receiver);
+        let idle = Arc::clone(&self.idle);
+        spawn(move || loop {
+            let next = receiver
+                .lock()
+                .unwrap()
+                .recv_timeout(CALLBACK_WORKER_IDLE_TIMEOUT);
+            match next {
+                Ok(cb) => {
+                    // A panicking callback must not take its worker down with it
+                    let _ = catch_unwind(AssertUnwindSafe(|| cb()));
+                    idle.fetch_add(1, Ordering::SeqCst);
+                }
+                // Exit unless a callback has already been promised to an idle worker
+                Err(RecvTimeoutError::Timeout) => {
+                    if take_one(&idle).is_ok() {
+                        return;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        });
+    }
+}
+
+fn take_one(count: &AtomicUsize) -> Result<usize, usize> {
+    count.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+}
+
+/// Runs a bound callback on a callback worker thread, so that the event loop isn't blocked.
+pub fn run_callback(cb: &Handler) {
+    CALLBACK_POOL.run(Arc::clone(cb));
+}
+
+/// Runs the press bind of `key` and returns whether the input should be blocked. A blockable
+/// callback runs after the bind map is unlocked, so that it can bind or unbind inputs itself.
+pub fn run_press_bind<K: Eq + Hash>(binds: &Mutex<HashMap<K, Bind>>, key: &K) -> bool {
+    let blockable = match binds.lock().unwrap().get(key) {
+        Some(Bind::Normal(cb)) => {
+            run_callback(cb);
+            return false;
+        }
+        Some(Bind::Block(cb)) => {
+            run_callback(cb);
+            return true;
+        }
+        Some(Bind::Blockable(cb)) => Arc::clone(cb),
+        Some(Bind::Release(_)) | None => return false,
+    };
+    matches!(blockable(), BlockInput::Block)
+}
+
+pub fn has_mouse_binds() -> bool {
+    !MOUSE_BINDS.lock().unwrap().is_empty() || !MOUSE_RELEASE_BINDS.lock().unwrap().is_empty()
+}
+
+pub fn has_keybd_binds() -> bool {
+    !KEYBD_BINDS.lock().unwrap().is_empty()
+        || !KEYBD_RELEASE_BINDS.lock().unwrap().is_empty()
+        || !KEYBD_CHORD_BINDS.lock().unwrap().is_empty()
+        || !KEYBD_CHORD_RELEASE_BINDS.lock().unwrap().is_empty()
+}
 
 pub fn should_continue(auto_stop: bool) -> bool {
     HANDLE_EVENTS.load(Ordering::Relaxed)
         && (!auto_stop
-            || !MOUSE_BINDS.lock().unwrap().is_empty()
-            || !KEYBD_BINDS.lock().unwrap().is_empty()
-            || !KEYBD_RELEASE_BINDS.lock().unwrap().is_empty()
-            || !MOUSE_RELEASE_BINDS.lock().unwrap().is_empty())
+            || has_mouse_binds()
+            || has_keybd_binds()
+            || has_event_subscribers())
 }
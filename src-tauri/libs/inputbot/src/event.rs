@@ -0,0 +1,124 @@
+use crate::{chord::*, common::*, public::*};
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    time::Duration,
+};
+
+/// An input event observed while `handle_input_events` is running.
+#[derive(Debug, PartialEq, Clone)]
+pub struct InputEvent {
+    /// Time of the event as reported by the OS. It is relative to an unspecified starting point
+    /// (usually boot), so only differences between events are meaningful.
+    pub time: Duration,
+    /// The device which produced the event. Not available on Windows.
+    pub device: Option<InputDevice>,
+    /// The modifiers held after the event.
+    pub modifiers: Modifiers,
+    pub kind: InputEventKind,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum InputEventKind {
+    KeyPress(KeybdKey),
+    KeyRelease(KeybdKey),
+    ButtonPress(MouseButton),
+    ButtonRelease(MouseButton),
+    /// Relative pointer motion in pixels.
+    Motion { dx: f64, dy: f64 },
+    /// Wheel rotation in clicks. Positive `dy` is away from the user, positive `dx` is right.
+    Wheel { dx: f64, dy: f64 },
+}
+
+/// Identity of an input device.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct InputDevice {
+    pub name: String,
+    pub vendor_id: u32,
+    pub product_id: u32,
+}
+
+impl InputEvent {
+    pub(crate) fn new(kind: InputEventKind, time: Duration, device: Option<InputDevice>) -> Self {
+        let mut modifiers = Modifiers::pressed();
+        // The OS key state may not include the event itself yet
+        match kind {
+            InputEventKind::KeyPress(key) => {
+                if let Some(modifier) = Modifier::from_key(key) {
+                    modifiers.insert(modifier);
+                }
+            }
+            InputEventKind::KeyRelease(key) => {
+                if let Some(modifier) = Modifier::from_key(key) {
+                    if !modifier.keys().into_iter().any(|k| k != key && k.is_pressed()) {
+                        modifiers.remove(modifier);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Self {
+            time,
+            device,
+            modifiers,
+            kind,
+        }
+    }
+}
+
+/// The sending end of a subscription.
+pub(crate) enum EventSubscriber {
+    Std(Sender<InputEvent>),
+    #[cfg(feature = "tokio")]
+    Tokio(tokio::sync::mpsc::UnboundedSender<InputEvent>),
+}
+
+impl EventSubscriber {
+    /// Returns false if the receiver is gone.
+    fn send(&self, event: InputEvent) -> bool {
+        match self {
+            EventSubscriber::Std(sender) => sender.send(event).is_ok(),
+            #[cfg(feature = "tokio")]
+            EventSubscriber::Tokio(sender) => sender.send(event).is_ok(),
+        }
+    }
+}
+
+/// Returns a receiver of all input events, whether they are bound or not.
+///
+/// Events are only delivered while `handle_input_events` is running. The subscription ends when
+/// the receiver is dropped.
+pub fn subscribe_events() -> Receiver<InputEvent> {
+    let (sender, receiver) = channel();
+    EVENT_SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .push(EventSubscriber::Std(sender));
+    receiver
+}
+
+/// Same as `subscribe_events`, but returns a Tokio receiver which can be awaited.
+#[cfg(feature = "tokio")]
+pub fn subscribe_events_async() -> tokio::sync::mpsc::UnboundedReceiver<InputEvent> {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    EVENT_SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .push(EventSubscriber::Tokio(sender));
+    receiver
+}
+
+pub(crate) fn has_event_subscribers() -> bool {
+    !EVENT_SUBSCRIBERS.lock().unwrap().is_empty()
+}
+
+/// Sends an event to all subscribers, dropping those whose receiver is gone. The event is only
+/// built if there are subscribers.
+pub(crate) fn publish_event<F: FnOnce() -> InputEvent>(make_event: F) {
+    let mut subscribers = EVENT_SUBSCRIBERS.lock().unwrap();
+    if subscribers.is_empty() {
+        return;
+    }
+    let event = make_event();
+    subscribers.retain(|subscriber| subscriber.send(event.clone()));
+}
@@ -5,6 +5,12 @@ mod common;
 mod public;
 pub use crate::public::*;
 
+mod chord;
+pub use crate::chord::*;
+
+mod event;
+pub use crate::event::*;
+
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
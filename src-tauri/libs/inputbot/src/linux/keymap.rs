@@ -0,0 +1,52 @@
+use crate::{
+    linux::inputs::key_to_scan_code,
+    public::{get_keybd_key, KeybdKey},
+};
+use std::collections::HashMap;
+
+/// A key press which produces a character.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct KeyStroke {
+    /// Evdev code of the key.
+    pub code: u32,
+    pub shift: bool,
+}
+
+/// Maps printable ASCII characters to the keys which produce them in the US layout.
+///
+/// The layout active in the compositor can't be read on Wayland, so this is always the US layout,
+/// and the keys produce other characters while a layout which isn't US compatible is active.
+/// Characters outside of ASCII are entered as Unicode input instead.
+pub struct Keymap {
+    strokes: HashMap<char, KeyStroke>,
+}
+
+impl Keymap {
+    /// Builds the US layout from the keys known to `get_keybd_key`.
+    pub fn us() -> Keymap {
+        let mut strokes = HashMap::new();
+        for c in (' '..='~').chain(['\t', '\n']) {
+            let key = match c {
+                '\t' => Some(KeybdKey::TabKey),
+                '\n' => Some(KeybdKey::EnterKey),
+                _ => get_keybd_key(c),
+            };
+            if let Some(key) = key {
+                let shift = c.is_ascii_uppercase() || "!@#$%^&*()_+{}|:\"<>?~".contains(c);
+                strokes.insert(
+                    c,
+                    KeyStroke {
+                        code: key_to_scan_code(key) as u32,
+                        shift,
+                    },
+                );
+            }
+        }
+        Keymap { strokes }
+    }
+
+    /// Returns the key press which produces a given character, if the layout has one.
+    pub fn get(&self, c: char) -> Option<KeyStroke> {
+        self.strokes.get(&c).copied()
+    }
+}
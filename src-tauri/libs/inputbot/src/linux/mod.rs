@@ -1,26 +1,39 @@
-use crate::{common::*, linux::inputs::*, public::*};
+use crate::{
+    chord::*,
+    common::*,
+    event::*,
+    linux::{inputs::*, keymap::*},
+    public::*,
+};
 use input::{
     event::{
+        device::DeviceEvent,
         keyboard::{
             KeyState, {KeyboardEvent, KeyboardEventTrait},
         },
-        pointer::{ButtonState, PointerEvent::*},
+        pointer::{
+            Axis, ButtonState, PointerEvent::*, PointerEventTrait, PointerScrollEvent,
+        },
         Event::{self, *},
+        EventTrait,
     },
-    Libinput, LibinputInterface,
+    Device as LibinputDevice, DeviceCapability, Libinput, LibinputInterface,
 };
 use nix::{
+    errno::Errno,
     fcntl::{open, OFlag},
+    poll::{poll, PollFd, PollFlags},
     sys::stat::Mode,
     unistd::close,
 };
 use once_cell::sync::Lazy;
 use std::{
+    collections::HashSet,
     mem::MaybeUninit,
-    os::fd::{FromRawFd, IntoRawFd, OwnedFd},
-    path::Path,
+    os::fd::{AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
     ptr::null,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
     sync::Mutex,
     thread::sleep,
     time::Duration,
@@ -33,18 +46,39 @@ use uinput::event::{
 use x11::xlib::*;
 
 mod inputs;
+mod keymap;
+mod xtest;
+
+// EVIOCGRAB: gives the caller exclusive access to an evdev device
+nix::ioctl_write_int!(eviocgrab, b'E', 0x90);
+
+const FAKE_DEVICE_NAME: &str = "inputbot";
+// How often the event loop wakes up without input to check whether it should stop
+const POLL_TIMEOUT_MS: i32 = 100;
 
 type ButtonStatesMap = HashMap<MouseButton, bool>;
 type KeyStatesMap = HashMap<KeybdKey, bool>;
+type DeviceFdsMap = HashMap<PathBuf, RawFd>;
 
 static BUTTON_STATES: Lazy<Mutex<ButtonStatesMap>> =
     Lazy::new(|| Mutex::new(ButtonStatesMap::new()));
 static KEY_STATES: Lazy<Mutex<KeyStatesMap>> = Lazy::new(|| Mutex::new(KeyStatesMap::new()));
+// File descriptors of the devices opened by libinput, so that they can be grabbed
+static DEVICE_FDS: Lazy<Mutex<DeviceFdsMap>> = Lazy::new(|| Mutex::new(DeviceFdsMap::new()));
+// Whether keyboards are grabbed, follows the presence of blocking keyboard binds
+static GRAB_KEYBOARDS: AtomicBool = AtomicBool::new(false);
+// Device paths of the keyboards which can be grabbed, by sysname
+static KEYBOARDS: Lazy<Mutex<HashMap<String, PathBuf>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// Sysnames of the grabbed devices, whose non-blocked events are re-emitted through FAKE_DEVICE
+static GRABBED_DEVICES: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+// Evdev codes of the blocked key presses, so that their releases are blocked too
+static BLOCKED_KEYS: Lazy<Mutex<HashSet<u32>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 static FAKE_DEVICE: Lazy<Mutex<uinput::Device>> = Lazy::new(|| {
     Mutex::new(
         uinput::default()
             .unwrap()
-            .name("inputbot")
+            .name(FAKE_DEVICE_NAME)
             .unwrap()
             .event(uinput::event::Keyboard::All)
             .unwrap()
@@ -72,10 +106,35 @@ static FAKE_DEVICE: Lazy<Mutex<uinput::Device>> = Lazy::new(|| {
             .unwrap(),
     )
 });
+// Null when there is no X server, or when Wayland is used and X would only reach XWayland clients
 static SEND_DISPLAY: Lazy<AtomicPtr<Display>> = Lazy::new(|| {
+    if display_server() == DisplayServer::Wayland {
+        return AtomicPtr::default();
+    }
     unsafe { XInitThreads() };
     AtomicPtr::new(unsafe { XOpenDisplay(null()) })
 });
+static KEYMAP: Lazy<Keymap> = Lazy::new(Keymap::us);
+
+/// The display server of the current session.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum DisplayServer {
+    X11,
+    Wayland,
+    /// No graphical session, e.g. a virtual console.
+    Unknown,
+}
+
+/// Detects the display server of the current session from the environment.
+pub fn display_server() -> DisplayServer {
+    match std::env::var("XDG_SESSION_TYPE").as_deref() {
+        Ok("wayland") => DisplayServer::Wayland,
+        Ok("x11") => DisplayServer::X11,
+        _ if std::env::var_os("WAYLAND_DISPLAY").is_some() => DisplayServer::Wayland,
+        _ if std::env::var_os("DISPLAY").is_some() => DisplayServer::X11,
+        _ => DisplayServer::Unknown,
+    }
+}
 
 /// Requests the fake device to be generated.
 ///
@@ -167,7 +226,7 @@ impl MouseCursor {
     }
 
     /// Moves the mouse to a given position based on absolute coordinates. The top left
-    /// corner of the screen is (0, 0).
+    /// corner of the screen is (0, 0). Not supported on Wayland.
     pub fn move_abs(x: i32, y: i32) {
         let mut device = FAKE_DEVICE.lock().unwrap();
 
@@ -212,6 +271,83 @@ impl MouseWheel {
     }
 }
 
+impl KeySequence<'_> {
+    /// Types the sequence, including any Unicode characters.
+    ///
+    /// On X11 each character's keysym is temporarily mapped to a spare keycode and typed through
+    /// XTest. On Wayland, or if that fails, the sequence is typed through the fake device:
+    /// printable ASCII characters are typed as US layout key presses, and other characters are
+    /// entered as Ctrl+Shift+U followed by their code point.
+    ///
+    /// The fake device has these limitations, since the compositor's active layout can't be read:
+    /// - It sends evdev keycodes, so the characters only come out as intended while a US
+    ///   compatible layout is active. With e.g. a Russian layout active, letters are typed as
+    ///   Cyrillic ones, including the hex digits `a`-`f` of Unicode input.
+    /// - Ctrl+Shift+U is only understood by GTK applications and by IBus. Elsewhere characters
+    ///   outside of ASCII are lost.
+    ///
+    /// Carriage returns are skipped like on Windows, so that `"\r\n"` is a single line break.
+    pub fn send(&self) {
+        let text: String = self.0.chars().filter(|&c| c != '\r').collect();
+        let sent = SEND_DISPLAY
+            .with(|display| unsafe { xtest::send_text(display, &text) })
+            .unwrap_or(false);
+        if !sent {
+            send_uinput(&text);
+        }
+    }
+}
+
+fn send_uinput(text: &str) {
+    for c in text.chars() {
+        match KEYMAP.get(c) {
+            Some(stroke) => send_key_stroke(stroke),
+            None => send_unicode_input(c),
+        }
+    }
+}
+
+fn send_key_stroke(stroke: KeyStroke) {
+    let mut device = FAKE_DEVICE.lock().unwrap();
+    let shift = key_to_scan_code(KeybdKey::LShiftKey);
+
+    if stroke.shift {
+        device.write(0x01, shift, 1).unwrap();
+    }
+    device.write(0x01, stroke.code as i32, 1).unwrap();
+    device.synchronize().unwrap();
+    sleep(Duration::from_millis(20));
+
+    device.write(0x01, stroke.code as i32, 0).unwrap();
+    if stroke.shift {
+        device.write(0x01, shift, 0).unwrap();
+    }
+    device.synchronize().unwrap();
+}
+
+fn send_unicode_input(c: char) {
+    let control = key_to_scan_code(KeybdKey::LControlKey);
+    let shift = key_to_scan_code(KeybdKey::LShiftKey);
+    let u = key_to_scan_code(KeybdKey::UKey);
+    {
+        let mut device = FAKE_DEVICE.lock().unwrap();
+        for (code, value) in [(control, 1), (shift, 1), (u, 1), (u, 0), (shift, 0), (control, 0)] {
+            device.write(0x01, code, value).unwrap();
+        }
+        device.synchronize().unwrap();
+    }
+
+    for digit in format!("{:x}", c as u32).chars() {
+        if let Some(stroke) = KEYMAP.get(digit) {
+            send_key_stroke(stroke);
+        }
+    }
+    send_key_stroke(KeyStroke {
+        code: key_to_scan_code(KeybdKey::SpaceKey) as u32,
+        shift: false,
+    });
+}
+
 struct LibinputInterfaceRaw;
 
 impl LibinputInterfaceRaw {
@@ -223,6 +359,7 @@ impl LibinputInterfaceRaw {
 impl LibinputInterface for LibinputInterfaceRaw {
     fn open_restricted(&mut self, path: &Path, flags: i32) -> std::result::Result<OwnedFd, i32> {
         if let Ok(fd) = open(path, OFlag::from_bits_truncate(flags), Mode::empty()) {
+            DEVICE_FDS.lock().unwrap().insert(path.to_path_buf(), fd);
             Ok(unsafe { OwnedFd::from_raw_fd(fd) })
         } else {
             Err(1)
@@ -230,45 +367,194 @@ impl LibinputInterface for LibinputInterfaceRaw {
     }
 
     fn close_restricted(&mut self, fd: OwnedFd) {
+        let raw_fd = fd.as_raw_fd();
+        DEVICE_FDS
+            .lock()
+            .unwrap()
+            .retain(|_, device_fd| *device_fd != raw_fd);
         let _ = close(fd.into_raw_fd());
     }
 }
 
 /// Starts listening for bound input events (otionally stopping when binds are removed).
+///
+/// While blocking keyboard binds exist, keyboards are grabbed so that bound keys don't reach
+/// other applications, and all other key events are re-emitted through the fake device. Binds
+/// are re-checked on every loop iteration, so keyboards are grabbed and released as blocking
+/// binds are added and removed. Keyboards which are also pointers are not grabbed.
 pub fn handle_input_events(auto_stop: bool) {
     let mut libinput_context = Libinput::new_with_udev(LibinputInterfaceRaw);
     libinput_context
         .udev_assign_seat(&LibinputInterfaceRaw.seat())
         .unwrap();
 
+    GRAB_KEYBOARDS.store(false, Ordering::Relaxed);
+
+    // The context outlives the loop, so the borrowed descriptor stays valid
+    let libinput_fd = unsafe { BorrowedFd::borrow_raw(libinput_context.as_raw_fd()) };
+
     HANDLE_EVENTS.store(true, Ordering::Relaxed);
     while should_continue(auto_stop) {
+        update_keyboard_grabs();
+
+        let mut poll_fds = [PollFd::new(&libinput_fd, PollFlags::POLLIN)];
+        match poll(&mut poll_fds, POLL_TIMEOUT_MS) {
+            Ok(0) | Err(Errno::EINTR) => continue,
+            result => {
+                result.unwrap();
+            }
+        }
+
         libinput_context.dispatch().unwrap();
 
         for event in libinput_context.by_ref() {
             handle_input_event(event);
         }
+    }
+
+    // Grabs are released when libinput closes the devices
+    GRAB_KEYBOARDS.store(false, Ordering::Relaxed);
+    KEYBOARDS.lock().unwrap().clear();
+    GRABBED_DEVICES.lock().unwrap().clear();
+    BLOCKED_KEYS.lock().unwrap().clear();
+}
+
+fn has_blocking_keybd_binds() -> bool {
+    let is_blocking = |bind: &Bind| matches!(bind, Bind::Block(_) | Bind::Blockable(_));
+    KEYBD_BINDS.lock().unwrap().values().any(is_blocking)
+        || KEYBD_CHORD_BINDS.lock().unwrap().values().any(is_blocking)
+}
+
+/// Grabs or releases the keyboards when blocking binds appear or disappear.
+fn update_keyboard_grabs() {
+    let grab = has_blocking_keybd_binds();
+    if GRAB_KEYBOARDS.swap(grab, Ordering::Relaxed) == grab {
+        return;
+    }
+
+    if grab {
+        init_device();
+        for (sysname, path) in KEYBOARDS.lock().unwrap().iter() {
+            grab_keyboard(sysname, path);
+        }
+    } else {
+        let keyboards = KEYBOARDS.lock().unwrap();
+        let device_fds = DEVICE_FDS.lock().unwrap();
+        for sysname in GRABBED_DEVICES.lock().unwrap().drain() {
+            if let Some(fd) = keyboards.get(&sysname).and_then(|path| device_fds.get(path)) {
+                let _ = unsafe { eviocgrab(*fd, 0) };
+            }
+        }
+        BLOCKED_KEYS.lock().unwrap().clear();
+    }
+}
 
-        sleep(Duration::from_millis(10));
+fn add_keyboard(device: &LibinputDevice) {
+    if device.name() == FAKE_DEVICE_NAME
+        || !device.has_capability(DeviceCapability::Keyboard)
+        || device.has_capability(DeviceCapability::Pointer)
+    {
+        return;
+    }
+
+    let sysname = device.sysname().to_owned();
+    let path = Path::new("/dev/input").join(&sysname);
+    if GRAB_KEYBOARDS.load(Ordering::Relaxed) {
+        grab_keyboard(&sysname, &path);
+    }
+    KEYBOARDS.lock().unwrap().insert(sysname, path);
+}
+
+fn grab_keyboard(sysname: &str, path: &Path) {
+    let Some(&fd) = DEVICE_FDS.lock().unwrap().get(path) else {
+        return;
+    };
+    if unsafe { eviocgrab(fd, 1) }.is_ok() {
+        GRABBED_DEVICES.lock().unwrap().insert(sysname.to_owned());
     }
 }
 
+/// Sends a key event from a grabbed keyboard on through the fake device, unless it was blocked.
+fn reemit_key(code: u32, pressed: bool, blocked: bool) {
+    let blocked = {
+        let mut blocked_keys = BLOCKED_KEYS.lock().unwrap();
+        if pressed {
+            if blocked {
+                blocked_keys.insert(code);
+            }
+            blocked
+        } else {
+            blocked_keys.remove(&code)
+        }
+    };
+    if blocked {
+        return;
+    }
+
+    let mut device = FAKE_DEVICE.lock().unwrap();
+    device.write(0x01, code as i32, pressed as i32).unwrap();
+    device.synchronize().unwrap();
+}
+
 fn handle_input_event(event: Event) {
     match event {
+        Device(DeviceEvent::Added(device_added_event)) => {
+            add_keyboard(&device_added_event.device());
+        }
+        Device(DeviceEvent::Removed(device_removed_event)) => {
+            let device = device_removed_event.device();
+            KEYBOARDS.lock().unwrap().remove(device.sysname());
+            GRABBED_DEVICES.lock().unwrap().remove(device.sysname());
+        }
         Keyboard(KeyboardEvent::Key(keyboard_key_event)) => {
+            let device = keyboard_key_event.device();
+            let grabbed = {
+                let grabbed_devices = GRABBED_DEVICES.lock().unwrap();
+                // Re-emitted events were already handled when they came from the grabbed keyboard
+                if !grabbed_devices.is_empty() && device.name() == FAKE_DEVICE_NAME {
+                    return;
+                }
+                grabbed_devices.contains(device.sysname())
+            };
+
             let key = keyboard_key_event.key();
+            let pressed = keyboard_key_event.key_state() == KeyState::Pressed;
+            let mut blocked = false;
             if let Some(keybd_key) = scan_code_to_key(key) {
-                if keyboard_key_event.key_state() == KeyState::Pressed {
+                if pressed {
                     KEY_STATES.lock().unwrap().insert(keybd_key, true);
-
-                    if let Some(Bind::Normal(cb)) = KEYBD_BINDS.lock().unwrap().get(&keybd_key) {
-                        let cb = Arc::clone(cb);
-                        spawn(move || cb());
-                    }
+                    publish_event(|| {
+                        input_event(
+                            InputEventKind::KeyPress(keybd_key),
+                            keyboard_key_event.time_usec(),
+                            &keyboard_key_event,
+                        )
+                    });
+
+                    blocked = run_press_bind(&KEYBD_BINDS, &keybd_key);
+                    blocked |= handle_chord_press(keybd_key);
                 } else {
                     KEY_STATES.lock().unwrap().insert(keybd_key, false);
+                    publish_event(|| {
+                        input_event(
+                            InputEventKind::KeyRelease(keybd_key),
+                            keyboard_key_event.time_usec(),
+                            &keyboard_key_event,
+                        )
+                    });
+
+                    if let Some(Bind::Release(cb)) =
+                        KEYBD_RELEASE_BINDS.lock().unwrap().get(&keybd_key)
+                    {
+                        run_callback(cb);
+                    }
+                    handle_chord_release(keybd_key);
                 }
             }
+
+            if grabbed {
+                reemit_key(key, pressed, blocked);
+            }
         }
         Pointer(Button(button_event)) => {
             let button = button_event.button();
@@ -282,31 +568,99 @@ fn handle_input_event(event: Event) {
             } {
                 if button_event.button_state() == ButtonState::Pressed {
                     BUTTON_STATES.lock().unwrap().insert(mouse_button, true);
+                    publish_event(|| {
+                        input_event(
+                            InputEventKind::ButtonPress(mouse_button),
+                            button_event.time_usec(),
+                            &button_event,
+                        )
+                    });
                     if let Some(Bind::Normal(cb)) = MOUSE_BINDS.lock().unwrap().get(&mouse_button) {
-                        let cb = Arc::clone(cb);
-                        spawn(move || cb());
+                        run_callback(cb);
                     };
                 } else {
                     BUTTON_STATES.lock().unwrap().insert(mouse_button, false);
+                    publish_event(|| {
+                        input_event(
+                            InputEventKind::ButtonRelease(mouse_button),
+                            button_event.time_usec(),
+                            &button_event,
+                        )
+                    });
+                    if let Some(Bind::Release(cb)) =
+                        MOUSE_RELEASE_BINDS.lock().unwrap().get(&mouse_button)
+                    {
+                        run_callback(cb);
+                    };
                 }
             }
         }
+        Pointer(Motion(motion_event)) => {
+            publish_event(|| {
+                input_event(
+                    InputEventKind::Motion {
+                        dx: motion_event.dx(),
+                        dy: motion_event.dy(),
+                    },
+                    motion_event.time_usec(),
+                    &motion_event,
+                )
+            });
+        }
+        Pointer(ScrollWheel(scroll_event)) => {
+            publish_event(|| {
+                // Values are in 120ths of a click, and positive vertical values point down
+                let clicks = |axis| {
+                    if scroll_event.has_axis(axis) {
+                        scroll_event.scroll_value_v120(axis) / 120.0
+                    } else {
+                        0.0
+                    }
+                };
+                input_event(
+                    InputEventKind::Wheel {
+                        dx: clicks(Axis::Horizontal),
+                        dy: -clicks(Axis::Vertical),
+                    },
+                    scroll_event.time_usec(),
+                    &scroll_event,
+                )
+            });
+        }
         _ => {}
     }
 }
 
+fn input_event<E: EventTrait>(kind: InputEventKind, time_usec: u64, event: &E) -> InputEvent {
+    let device = event.device();
+    InputEvent::new(
+        kind,
+        Duration::from_micros(time_usec),
+        Some(InputDevice {
+            name: device.name().to_owned(),
+            vendor_id: device.id_vendor(),
+            product_id: device.id_product(),
+        }),
+    )
+}
+
 trait DisplayAcquirable {
-    fn with<F, Z>(&self, cb: F) -> Z
+    /// Runs the callback with the display locked. Returns `None` without running it if there is
+    /// no display.
+    fn with<F, Z>(&self, cb: F) -> Option<Z>
     where
         F: FnOnce(*mut Display) -> Z;
 }
 
 impl DisplayAcquirable for AtomicPtr<Display> {
-    fn with<F, Z>(&self, cb: F) -> Z
+    fn with<F, Z>(&self, cb: F) -> Option<Z>
     where
         F: FnOnce(*mut Display) -> Z,
     {
         let display = self.load(Ordering::Relaxed);
+        if display.is_null() {
+            return None;
+        }
         unsafe {
             XLockDisplay(display);
         };
@@ -315,6 +669,6 @@ impl DisplayAcquirable for AtomicPtr<Display> {
             XFlush(display);
             XUnlockDisplay(display);
         };
-        cb_result
+        Some(cb_result)
     }
 }
@@ -0,0 +1,78 @@
+use std::{thread::sleep, time::Duration};
+use x11::{
+    xlib::{
+        Display, KeySym, XChangeKeyboardMapping, XDisplayKeycodes, XFree, XGetKeyboardMapping,
+        XSync,
+    },
+    xtest::XTestFakeKeyEvent,
+};
+
+// Gives clients time to pick up the changed mapping before the next character
+const KEY_DELAY: Duration = Duration::from_millis(5);
+
+const XK_RETURN: KeySym = 0xFF0D;
+const XK_TAB: KeySym = 0xFF09;
+
+/// Types text by temporarily mapping each character's keysym to a spare keycode, which works
+/// for any character regardless of the keyboard layout. Returns false if there is no spare
+/// keycode.
+///
+/// # Safety
+///
+/// `display` must be a valid, locked display.
+pub unsafe fn send_text(display: *mut Display, text: &str) -> bool {
+    let Some(keycode) = find_spare_keycode(display) else {
+        return false;
+    };
+
+    for c in text.chars() {
+        let mut keysyms = [char_to_keysym(c); 2];
+        XChangeKeyboardMapping(display, keycode, 2, keysyms.as_mut_ptr(), 1);
+        XSync(display, 0);
+        XTestFakeKeyEvent(display, keycode as u32, 1, 0);
+        XTestFakeKeyEvent(display, keycode as u32, 0, 0);
+        XSync(display, 0);
+        sleep(KEY_DELAY);
+    }
+
+    // Leave the keycode unmapped again
+    let mut empty: [KeySym; 2] = [0; 2];
+    XChangeKeyboardMapping(display, keycode, 2, empty.as_mut_ptr(), 1);
+    XSync(display, 0);
+    true
+}
+
+/// Finds a keycode without any keysyms.
+unsafe fn find_spare_keycode(display: *mut Display) -> Option<i32> {
+    let (mut min_keycode, mut max_keycode) = (0, 0);
+    XDisplayKeycodes(display, &mut min_keycode, &mut max_keycode);
+
+    let mut keysyms_per_keycode = 0;
+    let keysyms = XGetKeyboardMapping(
+        display,
+        min_keycode as u8,
+        max_keycode - min_keycode + 1,
+        &mut keysyms_per_keycode,
+    );
+    if keysyms.is_null() {
+        return None;
+    }
+
+    let per_keycode = keysyms_per_keycode as usize;
+    let spare = (min_keycode..=max_keycode).find(|&keycode| {
+        let offset = (keycode - min_keycode) as usize * per_keycode;
+        (0..per_keycode).all(|i| *keysyms.add(offset + i) == 0)
+    });
+    XFree(keysyms as *mut _);
+    spare
+}
+
+/// Latin-1 keysyms equal the character code, other characters use Unicode keysyms.
+fn char_to_keysym(c: char) -> KeySym {
+    match c {
+        '\n' => XK_RETURN,
+        '\t' => XK_TAB,
+        ' '..='~' | '\u{A0}'..='\u{FF}' => c as KeySym,
+        _ => 0x0100_0000 | c as KeySym,
+    }
+}
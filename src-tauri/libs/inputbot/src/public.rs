@@ -1,6 +1,4 @@
 use crate::common::*;
-use std::{thread::sleep, time::Duration};
-
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
@@ -247,7 +245,6 @@ impl KeybdKey {
             .insert(self, Bind::Normal(Arc::new(callback)));
     }
 
-    #[cfg(target_os = "windows")]
     pub fn bind_release<F: Fn() + Send + Sync + 'static>(self, callback: F) {
         KEYBD_RELEASE_BINDS
             .lock()
@@ -283,7 +280,6 @@ impl KeybdKey {
         }
     }
 
-    #[cfg(target_os = "windows")]
     pub fn bind_all_release<F: Fn(KeybdKey) + Send + Sync + Clone + 'static>(callback: F) {
         for key in KeybdKey::iter() {
             let callback = callback.clone();
@@ -306,6 +302,10 @@ impl KeybdKey {
         KEYBD_BINDS.lock().unwrap().remove(&self);
     }
 
+    pub fn unbind_release(self) {
+        KEYBD_RELEASE_BINDS.lock().unwrap().remove(&self);
+    }
+
     // the canonical_name is guaranteed to roundtrip to and from the serialization format.
     pub fn canonical_name(self) -> String {
         match self {
@@ -466,13 +466,9 @@ pub enum ParseError {
     ParseIntError {
         #[from]
         source: std::num::ParseIntError,
-        backtrace: std::backtrace::Backtrace,
     },
     #[error("Unknown format '{val}'")]
-    UnknownFormat {
-        val: String,
-        backtrace: std::backtrace::Backtrace,
-    },
+    UnknownFormat { val: String },
 }
 
 #[cfg(feature = "serde")]
@@ -497,10 +493,7 @@ impl std::str::FromStr for KeybdKey {
             return Ok(KeybdKey::OtherKey(*v));
         }
 
-        Err(ParseError::UnknownFormat {
-            val: s.to_string(),
-            backtrace: std::backtrace::Backtrace::capture(),
-        })
+        Err(ParseError::UnknownFormat { val: s.to_string() })
     }
 }
 
@@ -523,7 +516,6 @@ impl MouseButton {
             .insert(self, Bind::Normal(Arc::new(callback)));
     }
 
-    #[cfg(target_os = "windows")]
     pub fn bind_release<F: Fn() + Send + Sync + 'static>(self, callback: F) {
         MOUSE_RELEASE_BINDS
             .lock()
@@ -559,7 +551,6 @@ impl MouseButton {
         }
     }
 
-    #[cfg(target_os = "windows")]
     pub fn bind_all_release<F: Fn(MouseButton) + Send + Sync + Clone + 'static>(callback: F) {
         for btn in MouseButton::iter() {
             let callback = callback.clone();
@@ -582,6 +573,10 @@ impl MouseButton {
         MOUSE_BINDS.lock().unwrap().remove(&self);
     }
 
+    pub fn unbind_release(self) {
+        MOUSE_RELEASE_BINDS.lock().unwrap().remove(&self);
+    }
+
     pub fn canonical_name(self) -> String {
         format!("{}", &self)
     }
@@ -621,10 +616,7 @@ impl std::str::FromStr for MouseButton {
             return Ok(MouseButton::OtherButton(*v));
         }
 
-        Err(ParseError::UnknownFormat {
-            val: s.to_string(),
-            backtrace: std::backtrace::Backtrace::capture(),
-        })
+        Err(ParseError::UnknownFormat { val: s.to_string() })
     }
 }
 
@@ -757,45 +749,19 @@ pub fn get_keybd_key(c: char) -> Option<KeybdKey> {
 
 pub struct KeySequence<'a>(pub &'a str);
 
-impl KeySequence<'_> {
-    pub fn send(&self) {
-        for c in self.0.chars() {
-            let mut uppercase = false;
-
-            if let Some(keybd_key) = {
-                if c.is_uppercase()
-                    || [
-                        '!', '@', '#', '$', '%', '^', '&', '*', '(', ')', '_', '+', '{', '}', '|',
-                        ':', '"', '<', '>', '?', '~',
-                    ]
-                    .contains(&c)
-                {
-                    uppercase = true;
-                }
-
-                get_keybd_key(c)
-            } {
-                if uppercase {
-                    KeybdKey::LShiftKey.press();
-                }
-
-                keybd_key.press();
-                sleep(Duration::from_millis(20));
-                keybd_key.release();
-
-                if uppercase {
-                    KeybdKey::LShiftKey.release();
-                }
-            };
-        }
-    }
-}
-
 /// Stops `handle_input_events()` (threadsafe)
 pub fn stop_handling_input_events() {
     HANDLE_EVENTS.store(false, Ordering::Relaxed);
 }
 
+/// Returns true once `handle_input_events()` has started its loop and until it is stopped.
+///
+/// `handle_input_events()` resets the stop flag when its loop starts, so a stop requested from
+/// another thread before that is lost. Wait for this to become true before stopping.
+pub fn is_handling_input_events() -> bool {
+    HANDLE_EVENTS.load(Ordering::Relaxed)
+}
+
 #[cfg(test)]
 mod tests {
 
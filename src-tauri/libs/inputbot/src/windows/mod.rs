@@ -1,10 +1,11 @@
-use crate::{common::*, public::*};
+use crate::{chord::*, common::*, event::*, public::*};
 use once_cell::sync::Lazy;
 use std::{
     ffi::{c_int, c_short, c_ulong, c_ushort},
     mem::{size_of, MaybeUninit},
     ptr::null_mut,
     sync::atomic::AtomicPtr,
+    time::Duration,
 };
 use windows::Win32::{
     Foundation::{LPARAM, LRESULT, WPARAM},
@@ -12,7 +13,7 @@ use windows::Win32::{
         Input::KeyboardAndMouse::{
             GetAsyncKeyState, GetKeyState, MapVirtualKeyW, SendInput, INPUT, INPUT_0,
             INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP,
-            KEYEVENTF_SCANCODE, MAP_VIRTUAL_KEY_TYPE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN,
+            KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, MAP_VIRTUAL_KEY_TYPE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN,
             MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_XUP,
             MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_XDOWN,  MOUSEEVENTF_WHEEL, MOUSEINPUT,
             MOUSE_EVENT_FLAGS, VIRTUAL_KEY,
@@ -21,7 +22,8 @@ use windows::Win32::{
             CallNextHookEx, GetCursorPos, GetMessageW, KillTimer, SetCursorPos, SetTimer,
             SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT, MSG, MSLLHOOKSTRUCT,
             WH_KEYBOARD_LL, WH_MOUSE_LL, WINDOWS_HOOK_ID, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN,
-            WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEWHEEL, WM_RBUTTONDOWN,
+            WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE,
+            WM_MOUSEWHEEL, WM_RBUTTONDOWN,
             WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1,
             XBUTTON2,
         },
@@ -32,6 +34,8 @@ mod inputs;
 
 static KEYBD_HHOOK: Lazy<AtomicPtr<HHOOK>> = Lazy::new(AtomicPtr::default);
 static MOUSE_HHOOK: Lazy<AtomicPtr<HHOOK>> = Lazy::new(AtomicPtr::default);
+// Last cursor position seen by the mouse hook, to report motion relative to it
+static LAST_CURSOR_POS: Mutex<Option<(i32, i32)>> = Mutex::new(None);
 
 impl KeybdKey {
     /// Returns true if a given `KeybdKey` is currently pressed (in the down position).
@@ -134,12 +138,33 @@ impl MouseWheel {
     }
 }
 
+impl KeySequence<'_> {
+    /// Types the sequence as Unicode characters, regardless of the keyboard layout.
+    pub fn send(&self) {
+        for c in self.0.chars() {
+            match c {
+                // "\r\n" is typed as a single Enter
+                '\r' => {}
+                '\n' => {
+                    KeybdKey::EnterKey.press();
+                    KeybdKey::EnterKey.release();
+                }
+                '\t' => {
+                    KeybdKey::TabKey.press();
+                    KeybdKey::TabKey.release();
+                }
+                _ => send_unicode_input(c),
+            }
+        }
+    }
+}
+
 /// Starts listening for bound input events (otionally stopping when binds are removed).
 pub fn handle_input_events(auto_stop: bool) {
-    if !MOUSE_BINDS.lock().unwrap().is_empty() {
+    if has_mouse_binds() || has_event_subscribers() {
         set_hook(WH_MOUSE_LL, &MOUSE_HHOOK, mouse_proc);
     };
-    if !KEYBD_BINDS.lock().unwrap().is_empty() {
+    if has_keybd_binds() || has_event_subscribers() {
         set_hook(WH_KEYBOARD_LL, &KEYBD_HHOOK, keybd_proc);
     };
 
@@ -155,46 +180,27 @@ pub fn handle_input_events(auto_stop: bool) {
 }
 
 unsafe extern "system" fn keybd_proc(code: c_int, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    if KEYBD_BINDS.lock().unwrap().is_empty() {
+    if !has_keybd_binds() && !has_event_subscribers() {
         unset_hook(&KEYBD_HHOOK);
     } else if w_param.0 as u32 == WM_KEYDOWN || w_param.0 as u32 == WM_SYSKEYDOWN {
-        if let Some(bind) = KEYBD_BINDS
-            .lock()
-            .unwrap()
-            .get_mut(&KeybdKey::from(u64::from(
-                (*(l_param.0 as *const KBDLLHOOKSTRUCT)).vkCode,
-            )))
-        {
-            match bind {
-                Bind::Normal(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                }
-                Bind::Release(_) => {}
-                Bind::Block(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                    return LRESULT(1);
-                }
-                Bind::Blockable(cb) => {
-                    if let BlockInput::Block = cb() {
-                        return LRESULT(1);
-                    }
-                }
-            }
+        let llhs = &*(l_param.0 as *const KBDLLHOOKSTRUCT);
+        let key = KeybdKey::from(u64::from(llhs.vkCode));
+        publish_event(|| input_event(InputEventKind::KeyPress(key), llhs.time));
+        if run_press_bind(&KEYBD_BINDS, &key) {
+            return LRESULT(1);
+        }
+        if handle_chord_press(key) {
+            return LRESULT(1);
         }
     } else if w_param.0 as u32 == WM_KEYUP || w_param.0 as u32 == WM_SYSKEYUP {
-        if let Some(bind) = KEYBD_RELEASE_BINDS
-            .lock()
-            .unwrap()
-            .get_mut(&KeybdKey::from(u64::from(
-                (*(l_param.0 as *const KBDLLHOOKSTRUCT)).vkCode,
-            )))
-        {
+        let llhs = &*(l_param.0 as *const KBDLLHOOKSTRUCT);
+        let key = KeybdKey::from(u64::from(llhs.vkCode));
+        publish_event(|| input_event(InputEventKind::KeyRelease(key), llhs.time));
+        handle_chord_release(key);
+        if let Some(bind) = KEYBD_RELEASE_BINDS.lock().unwrap().get_mut(&key) {
             match bind {
                 Bind::Release(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
+                    run_callback(cb);
                 }
                 Bind::Normal(_) | Bind::Block(_) | Bind::Blockable(_) => {}
             }
@@ -216,38 +222,29 @@ fn hiword_signed(l: DWORD) -> c_short {
 }
 
 unsafe extern "system" fn mouse_proc(code: c_int, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    if MOUSE_BINDS.lock().unwrap().is_empty() {
+    if !has_mouse_binds() && !has_event_subscribers() {
         unset_hook(&MOUSE_HHOOK);
-    } else if let Some((event, key_up)) = identify_event(w_param, l_param) {
-        if key_up {
-            if let Some(bind) = MOUSE_RELEASE_BINDS.lock().unwrap().get_mut(&event) {
-                match bind {
-                    Bind::Release(cb) => {
-                        let cb = Arc::clone(cb);
-                        spawn(move || cb());
-                    }
-                    Bind::Normal(_) | Bind::Block(_) | Bind::Blockable(_) => {}
-                }
-            }
-        } else if let Some(bind) = MOUSE_BINDS.lock().unwrap().get_mut(&event) {
-            match bind {
-                Bind::Normal(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                }
-                Bind::Block(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                    return LRESULT(1);
-                }
-                Bind::Blockable(cb) => {
-                    if let BlockInput::Block = cb() {
-                        return LRESULT(1);
+    } else {
+        let button = identify_event(w_param, l_param);
+        let llhs = &*(l_param.0 as *const MSLLHOOKSTRUCT);
+        if let Some(kind) = mouse_event_kind(w_param, llhs, button) {
+            publish_event(|| input_event(kind, llhs.time));
+        }
+
+        if let Some((event, key_up)) = button {
+            if key_up {
+                if let Some(bind) = MOUSE_RELEASE_BINDS.lock().unwrap().get_mut(&event) {
+                    match bind {
+                        Bind::Release(cb) => {
+                            run_callback(cb);
+                        }
+                        Bind::Normal(_) | Bind::Block(_) | Bind::Blockable(_) => {}
                     }
                 }
-                Bind::Release(_) => {}
+            } else if run_press_bind(&MOUSE_BINDS, &event) {
+                return LRESULT(1);
             }
-        };
+        }
     }
     return CallNextHookEx(None, code, w_param, l_param);
 
@@ -293,6 +290,41 @@ unsafe extern "system" fn mouse_proc(code: c_int, w_param: WPARAM, l_param: LPAR
     }
 }
 
+fn input_event(kind: InputEventKind, time_ms: u32) -> InputEvent {
+    InputEvent::new(kind, Duration::from_millis(u64::from(time_ms)), None)
+}
+
+fn mouse_event_kind(
+    w_param: WPARAM,
+    llhs: &MSLLHOOKSTRUCT,
+    button: Option<(MouseButton, bool)>,
+) -> Option<InputEventKind> {
+    match w_param.0 as u32 {
+        WM_MOUSEMOVE => {
+            let (x, y) = LAST_CURSOR_POS
+                .lock()
+                .unwrap()
+                .replace((llhs.pt.x, llhs.pt.y))?;
+            Some(InputEventKind::Motion {
+                dx: f64::from(llhs.pt.x - x),
+                dy: f64::from(llhs.pt.y - y),
+            })
+        }
+        WM_MOUSEWHEEL => Some(InputEventKind::Wheel {
+            dx: 0.0,
+            dy: f64::from(hiword_signed(llhs.mouseData)) / 120.0,
+        }),
+        WM_MOUSEHWHEEL => Some(InputEventKind::Wheel {
+            dx: f64::from(hiword_signed(llhs.mouseData)) / 120.0,
+            dy: 0.0,
+        }),
+        _ => match button? {
+            (button, true) => Some(InputEventKind::ButtonRelease(button)),
+            (button, false) => Some(InputEventKind::ButtonPress(button)),
+        },
+    }
+}
+
 fn set_hook(
     hook_id: WINDOWS_HOOK_ID,
     hook_ptr: &AtomicPtr<HHOOK>,
@@ -355,3 +387,32 @@ fn send_keybd_input(flags: KEYBD_EVENT_FLAGS, key_code: KeybdKey) {
 
     unsafe { SendInput(&[input], size_of::<INPUT>() as c_int) };
 }
+
+fn send_unicode_input(c: char) {
+    let mut buffer = [0u16; 2];
+    let units = c.encode_utf16(&mut buffer);
+
+    // Characters outside the BMP are sent as a surrogate pair
+    let mut inputs = Vec::with_capacity(units.len() * 2);
+    for flags in [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP] {
+        for &unit in units.iter() {
+            let keybd = KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: unit,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            };
+
+            let mut input_u: INPUT_0 = unsafe { std::mem::zeroed() };
+            input_u.ki = keybd;
+
+            inputs.push(INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: input_u,
+            });
+        }
+    }
+
+    unsafe { SendInput(&inputs, size_of::<INPUT>() as c_int) };
+}
@@ -1,8 +1,16 @@
 use crate::modules::events::record::{set_event_channel_record_global, RecordEvent};
 use crate::modules::{
     audio::{device::get_microphones as get_audio_microphones, record, stop},
-    // transcribation::_local::inference,
-    input::paste_text,
+    history::{self, HistoryEntry},
+    hotkey,
+    input::{inject, InputAction},
+    postprocess,
+    settings::{self, Settings},
+    transcribation::{
+        _local::{inference, TranscribeOptions},
+        model_manager::ModelManager,
+        transcript::Transcript,
+    },
 };
 use tauri::{ipc::Channel, Manager};
 
@@ -11,25 +19,34 @@ pub fn set_event_channel_record(channel: Channel<RecordEvent>) {
     set_event_channel_record_global(channel);
 }
 
+/// Начинает запись. Параметры распознавания переопределяют настройки только для этой записи
 #[tauri::command]
-pub async fn start_record(device_id: &str) -> Result<(), String> {
-    let _ = record(device_id).await;
+pub async fn start_record(
+    device_id: &str,
+    options: Option<TranscribeOptions>,
+) -> Result<(), String> {
+    // Если устройство не передано, используем устройство из настроек
+    let device_id = match device_id {
+        "" => settings::get_settings().device_id.unwrap_or_default(),
+        id => id.to_string(),
+    };
+    let _ = record(&device_id, options.unwrap_or_default()).await;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn stop_record() {
-    let result = stop().await;
-    let _ = paste_text(&result.unwrap());
+pub async fn stop_record() -> Result<Transcript, String> {
+    let transcript = stop()
+        .await
+        .map_err(|e| format!("Ошибка распознавания: {}", e))?;
+    let actions = transcript.actions.clone();
+    tokio::task::spawn_blocking(move || inject(&actions))
+        .await
+        .map_err(|e| format!("Ошибка вставки текста: {}", e))?
+        .map_err(|e| format!("Ошибка вставки текста: {}", e))?;
+    Ok(transcript)
 }
 
-// #[tauri::command]
-// pub fn start_transcribation() -> String {
-//     let result = inference();
-//     let _ = paste_text(&result);
-//     result
-// }
-
 use serde::Serialize;
 #[derive(Clone, Serialize)]
 pub struct MonitorInfo {
@@ -49,8 +66,150 @@ pub fn get_monitor_info(app: tauri::AppHandle) -> Result<MonitorInfo, String> {
     }
 }
 
+#[tauri::command]
+pub fn get_settings() -> Settings {
+    settings::get_settings()
+}
+
+/// Проверяет и сохраняет настройки. При смене модели сразу загружает новую
+#[tauri::command]
+pub async fn set_settings(settings: Settings) -> Result<Settings, String> {
+    let current = settings::get_settings();
+    let model_changed = settings.model != current.model;
+    if model_changed {
+        ModelManager::model_path(&settings.model).map_err(|e| e.to_string())?;
+    }
+    settings::save_settings(settings.clone())
+        .map_err(|e| format!("Ошибка сохранения настроек: {}", e))?;
+    // Клавишу меняем только после сохранения, чтобы привязка не разошлась с настройками
+    if settings.push_to_talk_key != current.push_to_talk_key {
+        hotkey::set_push_to_talk_key(settings.push_to_talk_key.as_deref())
+            .map_err(|e| e.to_string())?;
+    }
+
+    if model_changed {
+        let model = settings.model.clone();
+        tokio::task::spawn_blocking(move || ModelManager::load(&model))
+            .await
+            .map_err(|e| format!("Ошибка загрузки модели: {}", e))?
+            .map_err(|e| format!("Ошибка загрузки модели: {}", e))?;
+    }
+    Ok(settings)
+}
+
+/// Назначает глобальную клавишу push-to-talk и сохраняет её в настройках
+#[tauri::command]
+pub fn set_push_to_talk_key(key: Option<String>) -> Result<(), String> {
+    let mut settings = settings::get_settings();
+    settings.push_to_talk_key = key.clone();
+    settings::save_settings(settings).map_err(|e| format!("Ошибка сохранения настроек: {}", e))?;
+    hotkey::set_push_to_talk_key(key.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Ждёт нажатия глобального сочетания клавиш и возвращает его. `None`, если запись отменена
+#[tauri::command]
+pub async fn capture_hotkey() -> Result<Option<String>, String> {
+    hotkey::capture_hotkey()
+        .await
+        .map_err(|e| format!("Ошибка записи сочетания клавиш: {}", e))
+}
+
+/// История распознавания, начиная с последних записей
+#[tauri::command]
+pub fn get_history() -> Result<Vec<HistoryEntry>, String> {
+    history::list_entries().map_err(|e| format!("Ошибка чтения истории: {}", e))
+}
+
+/// Поиск по тексту записей истории
+#[tauri::command]
+pub fn search_history(query: String) -> Result<Vec<HistoryEntry>, String> {
+    history::search_entries(&query).map_err(|e| format!("Ошибка поиска в истории: {}", e))
+}
+
+/// Повторно вставляет текст записи истории в активное окно
+#[tauri::command]
+pub async fn repaste_history_entry(id: String) -> Result<(), String> {
+    let entry = history::get_entry(&id).map_err(|e| e.to_string())?;
+    // Через inject, чтобы команда "удали это" могла удалить вставленный текст
+    tokio::task::spawn_blocking(move || inject(&[InputAction::Text(entry.text)]))
+        .await
+        .map_err(|e| format!("Ошибка вставки текста: {}", e))?
+        .map_err(|e| format!("Ошибка вставки текста: {}", e))
+}
+
+/// Распознаёт сохранённую запись заново текущей моделью и обновляет историю
+#[tauri::command]
+pub async fn retranscribe_history_entry(id: String) -> Result<HistoryEntry, String> {
+    let mut entry = history::get_entry(&id).map_err(|e| e.to_string())?;
+    let Some(path) = entry.audio_path.clone() else {
+        return Err("Аудио этой записи не сохранено".to_string());
+    };
+    // Запись распознаётся сама по себе, без контекста последней диктовки
+    let options = TranscribeOptions {
+        use_previous_context: Some(false),
+        ..Default::default()
+    };
+    let transcript = tokio::task::spawn_blocking(move || inference(&path, &options))
+        .await
+        .map_err(|e| format!("Ошибка распознавания: {}", e))?
+        .map_err(|e| format!("Ошибка распознавания: {}", e))?;
+
+    let settings = settings::get_settings();
+    entry.text = postprocess::process(&transcript.text, &settings.post_processing);
+    entry.model = settings.model;
+    entry.language = transcript.language.unwrap_or(settings.language);
+    history::update_entry(&entry).map_err(|e| format!("Ошибка сохранения истории: {}", e))?;
+    Ok(entry)
+}
+
+/// Распознаёт WAV файл из истории или любой внешний файл и возвращает фрагменты с временем
+#[tauri::command]
+pub async fn transcribe_file(
+    path: String,
+    options: Option<TranscribeOptions>,
+) -> Result<Transcript, String> {
+    let mut options = options.unwrap_or_default();
+    // Файл не связан с последней диктовкой, если явно не попросили обратное
+    options.use_previous_context.get_or_insert(false);
+    let mut transcript = tokio::task::spawn_blocking(move || inference(&path, &options))
+        .await
+        .map_err(|e| format!("Ошибка распознавания: {}", e))?
+        .map_err(|e| format!("Ошибка распознавания: {}", e))?;
+    // Как и при диктовке, обрабатывается только текст, сегменты остаются как есть
+    let settings = settings::get_settings();
+    transcript.text = postprocess::process(&transcript.text, &settings.post_processing);
+    Ok(transcript)
+}
+
+/// Удаляет запись истории вместе с её аудио
+#[tauri::command]
+pub fn delete_history_entry(id: String) -> Result<(), String> {
+    history::delete_entry(&id).map_err(|e| format!("Ошибка удаления записи: {}", e))
+}
+
 // Функция-обертка для Tauri
 #[tauri::command]
 pub fn get_microphones() -> Result<String, String> {
     get_audio_microphones().map_err(|e| format!("Ошибка получения микрофонов: {:?}", e))
 }
+
+/// Список моделей, доступных в директории моделей приложения
+#[tauri::command]
+pub fn get_models() -> Result<Vec<String>, String> {
+    ModelManager::list_models().map_err(|e| format!("Ошибка получения моделей: {}", e))
+}
+
+/// Имя загруженной в память модели
+#[tauri::command]
+pub fn get_current_model() -> Option<String> {
+    ModelManager::current_model()
+}
+
+/// Загружает модель и делает её текущей
+#[tauri::command]
+pub async fn load_model(name: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || ModelManager::load(&name))
+        .await
+        .map_err(|e| format!("Ошибка загрузки модели: {}", e))?
+        .map_err(|e| format!("Ошибка загрузки модели: {}", e))
+}
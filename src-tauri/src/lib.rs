@@ -3,9 +3,35 @@ mod commands;
 mod modules;
 mod utils;
 
+use modules::{audio::retention, hotkey, settings, transcribation::model_manager::ModelManager};
+
 fn setup_app(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     // Инициализируем глобальный AppHandle
     app::init_app_handle(app.handle().clone());
+    // Загружаем настройки до всего остального, от них зависят остальные модули
+    let settings = settings::load_settings().unwrap_or_else(|e| {
+        eprintln!("Не удалось загрузить настройки: {}", e);
+        settings::get_settings()
+    });
+    // Назначаем глобальную клавишу push-to-talk
+    if let Some(key) = settings.push_to_talk_key.as_deref() {
+        if let Err(e) = hotkey::set_push_to_talk_key(Some(key)) {
+            eprintln!("Не удалось назначить клавишу push-to-talk: {}", e);
+        }
+    }
+    // Удаляем записи, которые больше не нужно хранить
+    let recording_retention = settings.recording_retention;
+    std::thread::spawn(move || {
+        if let Err(e) = retention::enforce(recording_retention) {
+            eprintln!("Ошибка очистки записей: {}", e);
+        }
+    });
+    // Загружаем модель заранее в фоне, чтобы первое распознавание не ждало загрузки
+    std::thread::spawn(move || {
+        if let Err(e) = ModelManager::load(&settings.model) {
+            eprintln!("Не удалось загрузить модель {}: {}", settings.model, e);
+        }
+    });
     Ok(())
 }
 
@@ -33,9 +59,21 @@ pub fn run() {
             commands::get_microphones,
             commands::start_record,
             commands::stop_record,
-            // commands::start_transcribation,
             commands::set_event_channel_record,
             commands::get_monitor_info,
+            commands::get_models,
+            commands::get_current_model,
+            commands::load_model,
+            commands::get_settings,
+            commands::set_settings,
+            commands::set_push_to_talk_key,
+            commands::capture_hotkey,
+            commands::get_history,
+            commands::search_history,
+            commands::repaste_history_entry,
+            commands::retranscribe_history_entry,
+            commands::delete_history_entry,
+            commands::transcribe_file,
         ])
         .setup(setup_app)
         .run(tauri::generate_context!())
@@ -1,5 +1,9 @@
 pub mod audio;
 pub mod errors;
 pub mod events;
+pub mod history;
+pub mod hotkey;
 pub mod input;
+pub mod postprocess;
+pub mod settings;
 pub mod transcribation;
@@ -1,40 +1,77 @@
 pub mod device;
 pub mod peaks;
+pub mod resampler;
+pub mod retention;
 pub mod session;
+pub mod vad;
 pub mod wav_writer;
 
-pub type SampleType = i8;
+/// Формат сэмплов записи: 16 бит, моно, TARGET_SAMPLE_RATE
+pub type SampleType = i16;
+/// Частота дискретизации записи, которую ожидает Whisper
+pub const TARGET_SAMPLE_RATE: u32 = 16_000;
 
 use crate::modules::{
     audio::{
         device::get_input_device,
         peaks::send_peaks,
         session::RecordingSession,
-        wav_writer::{listen_for_completion, write_to_wav},
+        vad::detect_silence,
+        wav_writer::{subscribe_wav_events, wait_for_completion, wav_duration_secs, write_to_wav},
     },
     events::record::RecordEvent,
+    history::{self, HistoryEntry},
+    input::{actions_text, inject},
+    postprocess,
+    settings::get_settings,
+    transcribation::{
+        _local::{inference, inference_samples, TranscribeOptions},
+        prompt::remember_transcript,
+        streaming::{stream_transcribe, StreamingMode},
+        transcript::Transcript,
+    },
 };
+use crate::utils::get_current_timestamp;
 use anyhow::Result;
-use cpal::traits::DeviceTrait;
 use lazy_static::lazy_static;
 use std::sync::Arc;
 use tokio::{
-    sync::Mutex,
+    sync::{
+        broadcast::{self, error::RecvError},
+        Mutex,
+    },
+    task::JoinHandle,
     time::{sleep, Duration},
 };
 
-// const MAX_RECORDING_DURATION_SECS: u64 = 60 * 5;
-const MAX_RECORDING_DURATION_SECS: u64 = 5;
+const WAV_COMPLETION_TIMEOUT_SECS: u64 = 5;
+
+/// Куда попадает звук сессии
+enum AudioSink {
+    /// WAV файл в директории записей
+    File,
+    /// Сэмплы в памяти, на диск ничего не пишется
+    Memory(JoinHandle<Vec<SampleType>>),
+}
+
+/// Активная запись
+struct ActiveRecording {
+    session: RecordingSession,
+    sink: AudioSink,
+    /// Параметры распознавания этой записи
+    options: TranscribeOptions,
+}
 
 // Глобальное состояние текущей сессии
 lazy_static! {
-    static ref CURRENT_SESSION: Arc<Mutex<Option<RecordingSession>>> = Arc::new(Mutex::new(None));
+    static ref CURRENT_SESSION: Arc<Mutex<Option<ActiveRecording>>> = Arc::new(Mutex::new(None));
 }
 
-/// Записывает аудио с выбранного устройства
-pub async fn record(device_id: &str) -> Result<()> {
+/// Записывает аудио с выбранного устройства. Незаданные параметры распознавания берутся из настроек
+pub async fn record(device_id: &str, options: TranscribeOptions) -> Result<()> {
     println!("======================");
     println!("Запись c устройства {}", device_id);
+    let settings = get_settings();
 
     // Проверяем, нет ли уже активной сессии
     if CURRENT_SESSION.lock().await.is_some() {
@@ -42,55 +79,190 @@ pub async fn record(device_id: &str) -> Result<()> {
     }
 
     let device = get_input_device(device_id)?;
-    let config = device.default_input_config()?;
-    let sample_rate = config.sample_rate().0 as u32;
 
-    let mut session = RecordingSession::new();
+    let mut session = RecordingSession::new(device_id);
     let id = &session.id;
-    // Создаем подписчика для WAV записи до запуска
-    let wav_rx = session.subscribe();
-    tokio::spawn(write_to_wav(wav_rx, sample_rate, id.clone()));
+    // Создаем подписчика для WAV записи до запуска. Если записи не сохраняются, собираем звук в памяти
+    let sink = if settings.save_recordings {
+        let wav_rx = session.subscribe();
+        tokio::spawn(write_to_wav(wav_rx, TARGET_SAMPLE_RATE, id.clone()));
+        AudioSink::File
+    } else {
+        AudioSink::Memory(tokio::spawn(collect_samples(session.subscribe())))
+    };
     // Создаем подписчик для отправки пиков
     let peaks_tx = session.subscribe();
     tokio::spawn(send_peaks(peaks_tx));
-    // Следим за временем записи
-    tokio::spawn(watch_recording_time());
+    // Создаем подписчика для автоматической остановки по тишине
+    if settings.vad.enabled {
+        let vad_rx = session.subscribe();
+        tokio::spawn(detect_silence(vad_rx, settings.vad));
+    }
+    // Создаем подписчика для потокового распознавания, если оно включено
+    if settings.streaming_mode != StreamingMode::Off {
+        let streaming_rx = session.subscribe();
+        // Другую модель для промежуточного текста не загружаем: это пришлось бы делать на каждом окне
+        let streaming_options = TranscribeOptions {
+            model: None,
+            ..options.clone()
+        };
+        tokio::spawn(stream_transcribe(
+            streaming_rx,
+            settings.streaming_mode,
+            streaming_options,
+        ));
+    }
 
     // Запускаем запись
     session.start(&device)?;
     // Сохраняем сессию в глобальное состояние
     {
         let mut current_session = CURRENT_SESSION.lock().await;
-        *current_session = Some(session);
+        *current_session = Some(ActiveRecording {
+            session,
+            sink,
+            options,
+        });
     }
+    // Следим за временем записи. Запускаем после сохранения сессии, иначе таймер сразу завершится
+    tokio::spawn(watch_recording_time(settings.max_recording_duration_secs));
 
     RecordEvent::start().send();
     println!("Запись начата");
     Ok(())
 }
 
-/// Безопасно останавливает текущую запись
-pub async fn stop() -> Result<String> {
-    if let Some(mut session) = CURRENT_SESSION.lock().await.take() {
-        // Останавливаем сессию
-        session.stop();
-        println!("Сессия остановлена.");
-        RecordEvent::stop().send();
+/// Безопасно останавливает текущую запись и возвращает результат распознавания
+pub async fn stop() -> Result<Transcript> {
+    let Some(ActiveRecording {
+        mut session,
+        sink,
+        options,
+    }) = CURRENT_SESSION.lock().await.take()
+    else {
+        println!("Нет активной записи");
+        return Ok(Transcript::default());
+    };
+
+    // Подписываемся на события WAV до остановки, чтобы не пропустить завершение файла
+    let wav_events = subscribe_wav_events();
+    let id = session.id.clone();
+    let device_id = session.device_id.clone();
+    let started_at = session.started_at;
+
+    // Останавливаем сессию. Канал закроется при удалении сессии, после чего файл будет финализирован
+    session.stop();
+    drop(session);
+    println!("Сессия остановлена.");
+    RecordEvent::stop().send();
 
-        // Ожидаем завершения записи файла
-        tokio::spawn(listen_for_completion(5, |id, path| {
+    // Распознавание блокирующее, выполняем его вне асинхронного рантайма
+    let (mut transcript, audio_path, duration_secs) = match sink {
+        AudioSink::File => {
+            // Ожидаем завершения записи файла
+            let path = wait_for_completion(wav_events, &id, WAV_COMPLETION_TIMEOUT_SECS).await?;
             println!("Запись {} завершена: {}", id, path);
-        }));
+            let duration_secs = wav_duration_secs(&path).unwrap_or_default();
+            let transcript = {
+                let path = path.clone();
+                let options = options.clone();
+                tokio::task::spawn_blocking(move || inference(&path, &options)).await??
+            };
+            (transcript, Some(path), duration_secs)
+        }
+        AudioSink::Memory(collector) => {
+            let samples = collector.await?;
+            let duration_secs = samples.len() as f64 / TARGET_SAMPLE_RATE as f64;
+            let options = options.clone();
+            let transcript =
+                tokio::task::spawn_blocking(move || inference_samples(&samples, &options))
+                    .await??;
+            (transcript, None, duration_secs)
+        }
+    };
+    println!("Распознанный текст: {}", transcript.text);
+    let settings = get_settings();
+    // Дальше, в том числе во вставку и историю, идёт обработанный текст. Сегменты остаются как есть
+    transcript.actions =
+        postprocess::process_dictation(&transcript.text, &settings.post_processing);
+    transcript.text = actions_text(&transcript.actions);
+    RecordEvent::final_transcript(&transcript).send();
+    // Текст станет контекстом следующей диктовки, если контекст для неё включён
+    if options
+        .use_previous_context
+        .unwrap_or(settings.use_previous_context)
+    {
+        remember_transcript(&transcript.text);
     }
 
-    println!("Остановка записи");
-    Ok(String::new())
+    // Ошибка сохранения истории не должна мешать вставке текста.
+    // Запись без речи тоже сохраняем, если есть аудио: её можно прослушать или распознать заново
+    if audio_path.is_some() || !transcript.text.trim().is_empty() {
+        let entry = HistoryEntry {
+            id,
+            started_at,
+            finished_at: get_current_timestamp(),
+            device_id,
+            duration_secs,
+            model: options.model.unwrap_or(settings.model),
+            language: transcript.language.clone().unwrap_or(settings.language),
+            text: transcript.text.clone(),
+            audio_path,
+        };
+        if let Err(e) = history::add_entry(&entry) {
+            eprintln!("Ошибка сохранения истории: {}", e);
+        }
+    }
+    // Удаляем записи, которые больше не нужно хранить
+    let retention_policy = settings.recording_retention;
+    match tokio::task::spawn_blocking(move || retention::enforce(retention_policy)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => eprintln!("Ошибка очистки записей: {}", e),
+        Err(e) => eprintln!("Ошибка очистки записей: {}", e),
+    }
+    Ok(transcript)
+}
+
+// Собирает сэмплы сессии в памяти до закрытия канала
+async fn collect_samples(mut rx: broadcast::Receiver<Vec<SampleType>>) -> Vec<SampleType> {
+    let mut samples = Vec::new();
+    loop {
+        match rx.recv().await {
+            Ok(chunk) => samples.extend(chunk),
+            Err(RecvError::Lagged(skipped)) => {
+                println!("Пропущено {} сэмплов из-за отставания", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+    samples
+}
+
+/// Идёт ли сейчас запись
+pub async fn is_recording() -> bool {
+    CURRENT_SESSION.lock().await.is_some()
+}
+
+/// Останавливает запись и вставляет распознанный текст в активное окно
+pub async fn stop_and_paste() {
+    match stop().await {
+        Ok(transcript) => {
+            // Ввод блокирует поток на время набора текста
+            let actions = transcript.actions;
+            match tokio::task::spawn_blocking(move || inject(&actions)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => eprintln!("Ошибка вставки текста: {}", e),
+                Err(e) => eprintln!("Ошибка вставки текста: {}", e),
+            }
+        }
+        Err(e) => eprintln!("Ошибка остановки записи: {}", e),
+    }
 }
 
 // Следит за временем записи и останавливает её при превышении лимита
-async fn watch_recording_time() {
+async fn watch_recording_time(max_duration_secs: u64) {
     let mut elapsed = 0u64;
-    while elapsed < MAX_RECORDING_DURATION_SECS {
+    while elapsed < max_duration_secs {
         // Проверяем наличие сессии в каждой итерации
         let recording_active = CURRENT_SESSION.lock().await.is_some();
         if !recording_active {
@@ -105,8 +277,8 @@ async fn watch_recording_time() {
     if CURRENT_SESSION.lock().await.is_some() {
         println!(
             "Достигнут максимальный лимит записи ({} секунд)",
-            MAX_RECORDING_DURATION_SECS
+            max_duration_secs
         );
-        let _ = stop().await;
+        stop_and_paste().await;
     }
 }
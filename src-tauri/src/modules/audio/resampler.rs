@@ -0,0 +1,121 @@
+/// Сводит многоканальный поток в моно и меняет частоту дискретизации.
+/// Хранит состояние между чанками, поэтому подходит для потоковой обработки
+pub struct Resampler {
+    channels: usize,
+    // Сколько входных сэмплов приходится на один выходной
+    step: f64,
+    // Позиция следующего выходного сэмпла относительно начала `pending`
+    position: f64,
+    // Входные сэмплы (уже в моно), которые ещё не обработаны полностью
+    pending: Vec<f32>,
+}
+
+impl Resampler {
+    pub fn new(channels: u16, from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            step: from_rate as f64 / to_rate as f64,
+            position: 0.0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Обрабатывает очередной чанк с чередующимися каналами и возвращает моно сэмплы
+    pub fn process(&mut self, data: &[f32]) -> Vec<f32> {
+        self.downmix(data);
+
+        let mut output = Vec::with_capacity((self.pending.len() as f64 / self.step) as usize + 1);
+        if self.step >= 1.0 {
+            // Понижение частоты: усредняем входные сэмплы, попавшие в интервал выходного.
+            // Усреднение работает как простой фильтр нижних частот и уменьшает алиасинг
+            while self.position + self.step <= self.pending.len() as f64 {
+                let start = self.position as usize;
+                let end =
+                    ((self.position + self.step) as usize).clamp(start + 1, self.pending.len());
+                let window = &self.pending[start..end];
+                output.push(window.iter().sum::<f32>() / window.len() as f32);
+                self.position += self.step;
+            }
+        } else {
+            // Повышение частоты: линейная интерполяция между соседними сэмплами
+            while (self.position as usize) + 1 < self.pending.len() {
+                let index = self.position as usize;
+                let frac = (self.position - index as f64) as f32;
+                let current = self.pending[index];
+                let next = self.pending[index + 1];
+                output.push(current + (next - current) * frac);
+                self.position += self.step;
+            }
+        }
+
+        // Удаляем обработанные сэмплы, сохраняя дробную часть позиции
+        let consumed = (self.position as usize).min(self.pending.len());
+        self.pending.drain(..consumed);
+        self.position -= consumed as f64;
+
+        output
+    }
+
+    /// Сводит каналы в моно усреднением каждого кадра
+    fn downmix(&mut self, data: &[f32]) {
+        if self.channels == 1 {
+            self.pending.extend_from_slice(data);
+            return;
+        }
+        self.pending.extend(
+            data.chunks(self.channels)
+                .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resampler;
+
+    // Пилообразный сигнал, чтобы окна усреднения отличались друг от друга
+    fn ramp(len: usize) -> Vec<f32> {
+        (0..len).map(|i| (i % 100) as f32 / 100.0).collect()
+    }
+
+    #[test]
+    fn downmixes_stereo_to_mono() {
+        let mut resampler = Resampler::new(2, 16000, 16000);
+        let output = resampler.process(&[1.0, 0.0, 0.5, 0.5, -1.0, -0.5]);
+        assert_eq!(output, vec![0.5, 0.5, -0.75]);
+    }
+
+    #[test]
+    fn downsamples_48k_to_16k() {
+        let mut resampler = Resampler::new(1, 48000, 16000);
+        let output = resampler.process(&ramp(4800));
+        assert_eq!(output.len(), 1600);
+        // Каждый выходной сэмпл - среднее трёх входных
+        assert_eq!(output[1], (0.03 + 0.04 + 0.05) / 3.0);
+    }
+
+    #[test]
+    fn keeps_state_between_chunks() {
+        let input = ramp(4800);
+        let whole = Resampler::new(1, 48000, 16000).process(&input);
+
+        let mut resampler = Resampler::new(1, 48000, 16000);
+        let mut chunked = Vec::new();
+        for chunk in [&input[..1000], &input[1000..1001], &input[1001..]] {
+            chunked.extend(resampler.process(chunk));
+        }
+        assert_eq!(chunked, whole);
+    }
+
+    #[test]
+    fn keeps_state_between_stereo_chunks() {
+        let input: Vec<f32> = ramp(960).into_iter().flat_map(|s| [s, -s / 2.0]).collect();
+        let whole = Resampler::new(2, 48000, 16000).process(&input);
+
+        let mut resampler = Resampler::new(2, 48000, 16000);
+        let mut chunked = resampler.process(&input[..702]);
+        chunked.extend(resampler.process(&input[702..]));
+        assert_eq!(chunked.len(), 320);
+        assert_eq!(chunked, whole);
+    }
+}
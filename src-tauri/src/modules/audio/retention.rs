@@ -0,0 +1,127 @@
+use crate::modules::{
+    audio::wav_writer::{is_file_open, records_dir},
+    history,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+const WAV_EXTENSION: &str = "wav";
+const SECS_PER_DAY: u64 = 60 * 60 * 24;
+const BYTES_PER_MEGABYTE: u64 = 1024 * 1024;
+
+/// Сколько WAV файлов записей хранить на диске
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum RetentionPolicy {
+    /// Хранить все записи
+    #[default]
+    KeepAll,
+    /// Удалять запись сразу после распознавания
+    KeepNone,
+    /// Хранить только последние `count` записей
+    KeepLast { count: usize },
+    /// Хранить записи не дольше `days` дней
+    KeepDays { days: u64 },
+    /// Удалять старые записи, пока их общий размер больше `megabytes`
+    MaxSize { megabytes: u64 },
+}
+
+impl RetentionPolicy {
+    /// Проверяет корректность значений
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            RetentionPolicy::KeepLast { count: 0 } => Err(anyhow::anyhow!(
+                "Число хранимых записей должно быть больше нуля"
+            )),
+            RetentionPolicy::KeepDays { days: 0 } => Err(anyhow::anyhow!(
+                "Срок хранения записей должен быть больше нуля"
+            )),
+            RetentionPolicy::MaxSize { megabytes: 0 } => Err(anyhow::anyhow!(
+                "Размер хранилища записей должен быть больше нуля"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+struct RecordFile {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+/// Удаляет записи, которые не попадают под политику хранения, и возвращает их число.
+/// Файлы идущих записей не учитываются
+pub fn enforce(policy: RetentionPolicy) -> Result<usize> {
+    if policy == RetentionPolicy::KeepAll {
+        return Ok(0);
+    }
+
+    let mut files = list_record_files()?;
+    // Сначала новые записи
+    files.sort_by_key(|file| std::cmp::Reverse(file.modified));
+
+    let expired: Vec<RecordFile> = match policy {
+        RetentionPolicy::KeepAll => Vec::new(),
+        RetentionPolicy::KeepNone => files,
+        RetentionPolicy::KeepLast { count } => files.into_iter().skip(count).collect(),
+        RetentionPolicy::KeepDays { days } => {
+            let max_age = Duration::from_secs(days * SECS_PER_DAY);
+            files
+                .into_iter()
+                .filter(|file| file.modified.elapsed().unwrap_or_default() > max_age)
+                .collect()
+        }
+        RetentionPolicy::MaxSize { megabytes } => {
+            let max_size = megabytes * BYTES_PER_MEGABYTE;
+            let mut total = 0;
+            files
+                .into_iter()
+                .filter(|file| {
+                    total += file.size;
+                    total > max_size
+                })
+                .collect()
+        }
+    };
+
+    let mut removed = Vec::new();
+    for file in expired {
+        match fs::remove_file(&file.path) {
+            Ok(()) => removed.push(file.path.to_string_lossy().to_string()),
+            Err(e) => eprintln!("Не удалось удалить запись {}: {}", file.path.display(), e),
+        }
+    }
+
+    // История больше не должна ссылаться на удалённые файлы
+    if !removed.is_empty() {
+        history::forget_audio(&removed)?;
+        println!("Удалено старых записей: {}", removed.len());
+    }
+    Ok(removed.len())
+}
+
+fn list_record_files() -> Result<Vec<RecordFile>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(records_dir()?)? {
+        let path = entry?.path();
+        if !path.is_file()
+            || path.extension().and_then(|ext| ext.to_str()) != Some(WAV_EXTENSION)
+            || is_file_open(&path)
+        {
+            continue;
+        }
+        let metadata = fs::metadata(&path)?;
+        files.push(RecordFile {
+            path,
+            modified: metadata.modified()?,
+            size: metadata.len(),
+        });
+    }
+    Ok(files)
+}
@@ -1,4 +1,5 @@
-use crate::modules::audio::SampleType;
+use crate::modules::audio::{resampler::Resampler, SampleType, TARGET_SAMPLE_RATE};
+use crate::utils::get_current_timestamp;
 use cpal::{
     traits::{DeviceTrait, StreamTrait},
     FromSample, Sample, SizedSample,
@@ -14,16 +15,22 @@ const BUFFER_SIZE: usize = 48000 * 5;
 
 pub struct RecordingSession {
     pub id: String,
+    /// Устройство, с которого идёт запись
+    pub device_id: String,
+    /// Время создания сессии (unix, секунды)
+    pub started_at: u64,
     sender: broadcast::Sender<Vec<SampleType>>,
     stream: Option<cpal::Stream>,
 }
 
 impl RecordingSession {
-    pub fn new() -> Self {
+    pub fn new(device_id: &str) -> Self {
         let id = Uuid::new_v4().to_string();
         let (sender, _) = broadcast::channel::<Vec<SampleType>>(BUFFER_SIZE);
         Self {
             id,
+            device_id: device_id.to_string(),
+            started_at: get_current_timestamp(),
             sender,
             stream: None,
         }
@@ -64,9 +71,15 @@ impl RecordingSession {
     ) -> Result<cpal::Stream, anyhow::Error>
     where
         T: Sample + Send + SizedSample + 'static,
-        SampleType: Sample + FromSample<T>,
+        f32: FromSample<T>,
     {
         let config = device.default_input_config()?;
+        // Приводим звук к моно с частотой, на которой работает Whisper
+        let mut resampler = Resampler::new(
+            config.channels(),
+            config.sample_rate().0,
+            TARGET_SAMPLE_RATE,
+        );
 
         let err_fn = move |err| {
             eprintln!("Ошибка потока: {}", err);
@@ -77,11 +90,19 @@ impl RecordingSession {
         let stream = device.build_input_stream(
             &config.into(),
             move |data: &[T], _| {
-                // Преобразуем весь чанк
-                let samples: Vec<SampleType> = data
+                // Преобразуем весь чанк в f32, сводим каналы и меняем частоту
+                let input: Vec<f32> = data
                     .iter()
-                    .map(|&sample| SampleType::from_sample(sample))
+                    .map(|&sample| f32::from_sample(sample))
+                    .collect();
+                let samples: Vec<SampleType> = resampler
+                    .process(&input)
+                    .into_iter()
+                    .map(SampleType::from_sample)
                     .collect();
+                if samples.is_empty() {
+                    return;
+                }
 
                 // Отправляем весь чанк целиком
                 if let Err(e) = sender.send(samples) {
@@ -0,0 +1,74 @@
+use crate::modules::audio::{stop_and_paste, SampleType, TARGET_SAMPLE_RATE};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Настройки определения голосовой активности
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VadConfig {
+    /// Останавливать ли запись автоматически после паузы в речи
+    pub enabled: bool,
+    /// Порог громкости (RMS от 0.0 до 1.0), выше которого звук считается речью
+    pub threshold: f32,
+    /// Сколько речи нужно услышать, прежде чем тишина начнёт останавливать запись
+    pub min_speech_ms: u64,
+    /// Длительность тишины после речи, после которой запись останавливается
+    pub hangover_ms: u64,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 0.02,
+            min_speech_ms: 300,
+            hangover_ms: 1500,
+        }
+    }
+}
+
+/// Подписчик сессии, останавливающий запись после тишины, следующей за речью
+pub async fn detect_silence(mut vad_rx: broadcast::Receiver<Vec<SampleType>>, config: VadConfig) {
+    let mut speech_ms = 0.0;
+    let mut silence_ms = 0.0;
+
+    loop {
+        let samples = match vad_rx.recv().await {
+            Ok(samples) => samples,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        if samples.is_empty() {
+            continue;
+        }
+
+        let duration_ms = samples.len() as f64 * 1000.0 / TARGET_SAMPLE_RATE as f64;
+        if rms(&samples) >= config.threshold {
+            speech_ms += duration_ms;
+            silence_ms = 0.0;
+        } else if speech_ms >= config.min_speech_ms as f64 {
+            silence_ms += duration_ms;
+        }
+
+        if speech_ms >= config.min_speech_ms as f64 && silence_ms >= config.hangover_ms as f64 {
+            println!(
+                "Обнаружена тишина ({} мс), останавливаем запись",
+                silence_ms as u64
+            );
+            tokio::spawn(stop_and_paste());
+            break;
+        }
+    }
+}
+
+/// Среднеквадратичная громкость чанка, нормализованная к диапазону от 0.0 до 1.0
+fn rms(samples: &[SampleType]) -> f32 {
+    let sum: f64 = samples
+        .iter()
+        .map(|&sample| {
+            let value = sample as f64 / SampleType::MAX as f64;
+            value * value
+        })
+        .sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
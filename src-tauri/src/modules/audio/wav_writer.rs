@@ -2,16 +2,23 @@ use crate::app::get_local_data_dir;
 use crate::modules::audio::SampleType;
 use crate::utils::get_current_timestamp;
 use anyhow::Result;
-use hound::{WavSpec, WavWriter};
+use hound::{WavReader, WavSpec, WavWriter};
 use lazy_static::lazy_static;
-use std::{fs::File, io::BufWriter, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 use tokio::{
     sync::broadcast::{self, error::RecvError},
     sync::watch,
     time::{timeout, Duration},
 };
 
-const BITS_PER_SAMPLE: u16 = 8;
+const BITS_PER_SAMPLE: u16 = 16;
+const RECORDS_DIR: &str = "records";
 
 #[derive(Clone, Debug)]
 pub enum WavEvent {
@@ -26,6 +33,8 @@ lazy_static! {
         let (sender, _) = watch::channel(WavEvent::None);
         sender
     };
+    // Файлы, запись в которые ещё идёт
+    static ref OPEN_FILES: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
 }
 
 // Функция для отправки события
@@ -45,6 +54,8 @@ pub struct AudioFileWriter {
 impl AudioFileWriter {
     pub fn create(id: String, sample_rate: u32) -> Result<Self> {
         let path = Self::generate_wav_path(id.clone())?;
+        // Отмечаем файл до создания, чтобы очистка записей не удалила его
+        OPEN_FILES.lock().unwrap().insert(path.clone());
         let writer = WavWriter::create(
             &path,
             WavSpec {
@@ -53,7 +64,10 @@ impl AudioFileWriter {
                 bits_per_sample: BITS_PER_SAMPLE,
                 sample_format: hound::SampleFormat::Int,
             },
-        )?;
+        )
+        .inspect_err(|_| {
+            OPEN_FILES.lock().unwrap().remove(&path);
+        })?;
 
         Ok(Self { writer, id, path })
     }
@@ -68,7 +82,9 @@ impl AudioFileWriter {
 
     /// Завершает запись и закрывает файл
     pub fn finalize(self) -> Result<()> {
-        self.writer.finalize()?;
+        let result = self.writer.finalize();
+        OPEN_FILES.lock().unwrap().remove(&self.path);
+        result?;
 
         // Отправляем событие о завершении записи
         send_wav_event(WavEvent::RecordingComplete {
@@ -81,13 +97,7 @@ impl AudioFileWriter {
 
     fn generate_wav_path(id: String) -> Result<PathBuf> {
         let timestamp = get_current_timestamp();
-        let wav_path = format!("{}/{}_{}.wav", "records", timestamp, id);
-        let full_path = get_local_data_dir(&wav_path)?;
-        let path = PathBuf::from(full_path);
-        // Создаем директорию для записей если её нет
-        if let Some(parent) = path.parent() {
-            let _ = std::fs::create_dir_all(parent)?;
-        }
+        let path = records_dir()?.join(format!("{}_{}.wav", timestamp, id));
         println!("path: {}", path.display());
         Ok(path)
     }
@@ -144,36 +154,64 @@ pub async fn write_to_wav(
     println!("WAV запись завершена");
 }
 
-/// Прослушивает события завершения записи WAV файла
-/// F - замыкание, которое будет вызвано при получении события
-pub async fn listen_for_completion<F>(timeout_secs: u64, callback: F) -> Result<()>
-where
-    F: FnOnce(String, String) + Send + 'static,
-{
-    let mut rx = WAV_EVENTS.subscribe();
+/// Идёт ли ещё запись в файл
+pub fn is_file_open(path: &Path) -> bool {
+    OPEN_FILES.lock().unwrap().contains(path)
+}
+
+/// Подписывается на события WAV. Подписку нужно оформить до остановки сессии,
+/// чтобы не пропустить событие о завершении записи
+pub fn subscribe_wav_events() -> watch::Receiver<WavEvent> {
+    WAV_EVENTS.subscribe()
+}
+
+/// Ожидает завершения записи WAV файла указанной сессии и возвращает путь к нему
+pub async fn wait_for_completion(
+    mut rx: watch::Receiver<WavEvent>,
+    session_id: &str,
+    timeout_secs: u64,
+) -> Result<String> {
     println!("Начало прослушивания событий WAV");
 
-    // Ждем изменения значения
-    match timeout(Duration::from_secs(timeout_secs), rx.changed()).await {
-        Ok(Ok(_)) => match rx.borrow().clone() {
-            WavEvent::RecordingComplete { id, path } => {
-                println!("Получено событие WAV: запись завершена");
-                callback(id, path);
-                Ok(())
-            }
-            WavEvent::RecordingError { id, error } => {
-                eprintln!("Получено событие WAV: ошибка записи {} - {}", id, error);
-                Err(anyhow::anyhow!("Ошибка записи: {}", error))
+    let wait = async {
+        loop {
+            // Ждем изменения значения
+            rx.changed()
+                .await
+                .map_err(|e| anyhow::anyhow!("Ошибка получения события: {}", e))?;
+            match rx.borrow_and_update().clone() {
+                WavEvent::RecordingComplete { id, path } if id == session_id => {
+                    println!("Получено событие WAV: запись завершена");
+                    return Ok(path);
+                }
+                WavEvent::RecordingError { id, error } if id == session_id => {
+                    eprintln!("Получено событие WAV: ошибка записи {} - {}", id, error);
+                    return Err(anyhow::anyhow!("Ошибка записи: {}", error));
+                }
+                // События других сессий пропускаем
+                _ => continue,
             }
-            WavEvent::None => Err(anyhow::anyhow!("Получено пустое событие")),
-        },
-        Ok(Err(e)) => {
-            eprintln!("Ошибка получения события WAV: {}", e);
-            Err(anyhow::anyhow!("Ошибка получения события: {}", e))
         }
+    };
+
+    match timeout(Duration::from_secs(timeout_secs), wait).await {
+        Ok(result) => result,
         Err(_) => {
             eprintln!("Таймаут ожидания события WAV");
             Err(anyhow::anyhow!("Таймаут ожидания события"))
         }
     }
 }
+
+/// Директория с записями. Создаётся, если её нет
+pub fn records_dir() -> Result<PathBuf> {
+    let path = PathBuf::from(get_local_data_dir(RECORDS_DIR)?);
+    std::fs::create_dir_all(&path)?;
+    Ok(path)
+}
+
+/// Длительность WAV файла в секундах
+pub fn wav_duration_secs(path: &str) -> Result<f64> {
+    let reader = WavReader::open(path)?;
+    Ok(reader.duration() as f64 / reader.spec().sample_rate as f64)
+}
@@ -1,4 +1,4 @@
-use crate::modules::audio::SampleType;
+use crate::modules::{audio::SampleType, transcribation::transcript::Transcript};
 use crate::utils::get_current_timestamp;
 use lazy_static::lazy_static;
 use serde::Serialize;
@@ -28,6 +28,13 @@ pub enum RecordEvent {
     Progress { timestamp: u64, peak: SampleType },
     #[serde(rename_all = "camelCase")]
     Stop { timestamp: u64 },
+    #[serde(rename_all = "camelCase")]
+    Partial { timestamp: u64, text: String },
+    #[serde(rename_all = "camelCase")]
+    Final {
+        timestamp: u64,
+        transcript: Transcript,
+    },
 }
 
 impl RecordEvent {
@@ -47,6 +54,20 @@ impl RecordEvent {
             timestamp: get_current_timestamp(),
         }
     }
+    /// Промежуточный текст, пока запись продолжается
+    pub fn partial(text: &str) -> Self {
+        RecordEvent::Partial {
+            timestamp: get_current_timestamp(),
+            text: text.to_string(),
+        }
+    }
+    /// Окончательный результат после остановки записи
+    pub fn final_transcript(transcript: &Transcript) -> Self {
+        RecordEvent::Final {
+            timestamp: get_current_timestamp(),
+            transcript: transcript.clone(),
+        }
+    }
     pub fn send(&self) {
         if let Some(channel) = get_event_channel_record() {
             channel.send(self.clone()).unwrap();
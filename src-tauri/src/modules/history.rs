@@ -0,0 +1,174 @@
+use crate::app::get_local_data_dir;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+lazy_static! {
+    // Файл истории перезаписывается целиком при изменении записей
+    static ref HISTORY_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Запись истории распознавания
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Идентификатор сессии записи
+    pub id: String,
+    /// Время начала записи (unix, секунды)
+    pub started_at: u64,
+    /// Время окончания распознавания (unix, секунды)
+    pub finished_at: u64,
+    /// Устройство записи
+    pub device_id: String,
+    /// Длительность записи в секундах
+    pub duration_secs: f64,
+    /// Модель, которой распознан текст
+    pub model: String,
+    /// Язык распознанной речи
+    pub language: String,
+    /// Распознанный текст. Пустой, если речь не распознана
+    pub text: String,
+    /// Путь к WAV файлу записи, если он сохранён
+    pub audio_path: Option<String>,
+}
+
+/// Добавляет запись в конец истории
+pub fn add_entry(entry: &HistoryEntry) -> Result<()> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_path()?)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Возвращает историю, начиная с последних записей
+pub fn list_entries() -> Result<Vec<HistoryEntry>> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut entries = read_entries()?;
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Ищет записи, текст которых содержит запрос, без учёта регистра
+pub fn search_entries(query: &str) -> Result<Vec<HistoryEntry>> {
+    let query = query.to_lowercase();
+    Ok(list_entries()?
+        .into_iter()
+        .filter(|entry| entry.text.to_lowercase().contains(&query))
+        .collect())
+}
+
+/// Возвращает запись по идентификатору
+pub fn get_entry(id: &str) -> Result<HistoryEntry> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    read_entries()?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| anyhow::anyhow!("Запись {} не найдена в истории", id))
+}
+
+/// Заменяет запись с тем же идентификатором
+pub fn update_entry(updated: &HistoryEntry) -> Result<()> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut entries = read_entries()?;
+    let entry = entries
+        .iter_mut()
+        .find(|entry| entry.id == updated.id)
+        .ok_or_else(|| anyhow::anyhow!("Запись {} не найдена в истории", updated.id))?;
+    *entry = updated.clone();
+    write_entries(&entries)
+}
+
+/// Удаляет запись из истории вместе с её WAV файлом
+pub fn delete_entry(id: &str) -> Result<()> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut entries = read_entries()?;
+    let index = entries
+        .iter()
+        .position(|entry| entry.id == id)
+        .ok_or_else(|| anyhow::anyhow!("Запись {} не найдена в истории", id))?;
+    let entry = entries.remove(index);
+    write_entries(&entries)?;
+
+    if let Some(path) = entry.audio_path {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Не удалось удалить файл записи {}: {}", path, e);
+        }
+    }
+    Ok(())
+}
+
+/// Убирает из истории ссылки на удалённые WAV файлы. Текст записей сохраняется
+pub fn forget_audio(paths: &[String]) -> Result<()> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut entries = read_entries()?;
+    let mut changed = false;
+    for entry in entries.iter_mut() {
+        if entry
+            .audio_path
+            .as_ref()
+            .is_some_and(|path| paths.contains(path))
+        {
+            entry.audio_path = None;
+            changed = true;
+        }
+    }
+    if changed {
+        write_entries(&entries)?;
+    }
+    Ok(())
+}
+
+fn history_path() -> Result<PathBuf> {
+    let path = PathBuf::from(get_local_data_dir(HISTORY_FILE)?);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+/// Читает историю в порядке добавления. Повреждённые строки пропускаются
+fn read_entries() -> Result<Vec<HistoryEntry>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("Пропущена повреждённая запись истории: {}", e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Перезаписывает историю через временный файл, чтобы не потерять её при сбое
+fn write_entries(entries: &[HistoryEntry]) -> Result<()> {
+    let path = history_path()?;
+    let tmp_path = path.with_extension("jsonl.tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        for entry in entries {
+            writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+        }
+        writer.flush()?;
+    }
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
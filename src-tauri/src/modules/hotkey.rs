@@ -0,0 +1,195 @@
+use crate::modules::{
+    audio::{is_recording, record, stop_and_paste},
+    settings::get_settings,
+    transcribation::_local::TranscribeOptions,
+};
+use anyhow::Result;
+use inputbot::{InputEvent, InputEventKind, KeybdChord, KeybdKey, Modifier};
+use lazy_static::lazy_static;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+lazy_static! {
+    // Текущая клавиша push-to-talk
+    static ref PUSH_TO_TALK_KEY: Mutex<Option<KeybdChord>> = Mutex::new(None);
+    // Поток, в котором inputbot слушает глобальные события ввода
+    static ref LISTENER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+    // Запуск и остановка записи по клавише выполняются по очереди,
+    // иначе при коротком нажатии остановка может опередить запуск
+    static ref PUSH_TO_TALK_TASK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+// Клавиша удерживается. Нужно, чтобы игнорировать автоповтор нажатия
+static IS_HELD: AtomicBool = AtomicBool::new(false);
+// Клавишу отпустили, а запись ещё не остановлена
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+// Сколько ждать нажатия при записи сочетания клавиш
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(15);
+// Как часто проверять, запустился ли цикл обработки событий
+const LISTENER_START_POLL: Duration = Duration::from_millis(1);
+
+/// Назначает глобальную клавишу или сочетание клавиш push-to-talk (например "Ctrl+Alt+Space").
+/// `None` снимает назначение
+pub fn set_push_to_talk_key(key: Option<&str>) -> Result<()> {
+    let key = key
+        .map(|name| {
+            KeybdChord::from_str(name)
+                .map_err(|e| anyhow::anyhow!("Неизвестное сочетание клавиш: {}", e))
+        })
+        .transpose()?;
+
+    let mut current = PUSH_TO_TALK_KEY.lock().unwrap();
+    if let Some(previous) = current.take() {
+        // Ту же клавишу не снимаем, иначе список привязок на мгновение опустеет
+        if Some(previous) != key {
+            unbind(previous);
+        }
+    }
+
+    match key {
+        Some(key) => {
+            bind(key);
+            *current = Some(key);
+            start_listener();
+            println!("Клавиша push-to-talk: {}", key.canonical_name());
+        }
+        None => {
+            stop_listener();
+            println!("Клавиша push-to-talk снята");
+        }
+    }
+    Ok(())
+}
+
+/// Ждёт следующее глобальное сочетание клавиш и возвращает его в формате inputbot
+/// (например "Control+Alt+Space"). Escape отменяет запись и возвращает `None`
+pub async fn capture_hotkey() -> Result<Option<String>> {
+    let events = inputbot::subscribe_events_async();
+
+    // Пока идёт запись, push-to-talk не должен срабатывать
+    let current = *PUSH_TO_TALK_KEY.lock().unwrap();
+    if let Some(key) = current {
+        unbind(key);
+    }
+    start_listener();
+
+    let result = tokio::time::timeout(CAPTURE_TIMEOUT, wait_for_chord(events))
+        .await
+        .map_err(|_| anyhow::anyhow!("Сочетание клавиш не было нажато"));
+
+    // Клавиша могла смениться во время записи, поэтому восстанавливаем текущую
+    match *PUSH_TO_TALK_KEY.lock().unwrap() {
+        Some(key) => bind(key),
+        None => stop_listener(),
+    }
+    result
+}
+
+/// Возвращает первое сочетание: модификаторы и обычную клавишу или одни модификаторы,
+/// которые фиксируются при отпускании последнего нажатого
+async fn wait_for_chord(mut events: UnboundedReceiver<InputEvent>) -> Option<String> {
+    let mut last_modifier: Option<InputEvent> = None;
+
+    while let Some(event) = events.recv().await {
+        let chord_of = |event: &InputEvent, key: KeybdKey| {
+            let modifiers: Vec<Modifier> = event.modifiers.iter().collect();
+            KeybdChord::new(&modifiers, key).canonical_name()
+        };
+        match event.kind {
+            InputEventKind::KeyPress(KeybdKey::EscapeKey) => return None,
+            InputEventKind::KeyPress(key) if Modifier::from_key(key).is_some() => {
+                last_modifier = Some(event);
+            }
+            InputEventKind::KeyPress(key) => return Some(chord_of(&event, key)),
+            InputEventKind::KeyRelease(key) => {
+                if let Some(pressed) = &last_modifier {
+                    if pressed.kind == InputEventKind::KeyPress(key) {
+                        return Some(chord_of(pressed, key));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn bind(key: KeybdChord) {
+    // Блокируем нажатие, чтобы клавиша не попадала в активное приложение
+    key.block_bind(on_press);
+    key.bind_release(on_release);
+}
+
+fn unbind(key: KeybdChord) {
+    key.unbind();
+    key.unbind_release();
+}
+
+/// Запускает прослушивание событий ввода, если оно ещё не запущено, и ждёт начала цикла
+fn start_listener() {
+    let mut listener = LISTENER.lock().unwrap();
+    if listener
+        .as_ref()
+        .is_some_and(|handle| !handle.is_finished())
+    {
+        return;
+    }
+    let handle = std::thread::spawn(|| inputbot::handle_input_events(false));
+    // Цикл сбрасывает флаг остановки при запуске, поэтому остановка до этого момента потерялась бы
+    while !inputbot::is_handling_input_events() && !handle.is_finished() {
+        std::thread::sleep(LISTENER_START_POLL);
+    }
+    *listener = Some(handle);
+}
+
+/// Останавливает прослушивание событий ввода и дожидается завершения потока
+fn stop_listener() {
+    if let Some(handle) = LISTENER.lock().unwrap().take() {
+        inputbot::stop_handling_input_events();
+        let _ = handle.join();
+    }
+}
+
+fn on_press() {
+    if IS_HELD.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    tauri::async_runtime::spawn(async {
+        let _task = PUSH_TO_TALK_TASK.lock().await;
+        // Клавишу уже отпустили и остановка прошла раньше запуска
+        if !IS_HELD.load(Ordering::SeqCst) || is_recording().await {
+            return;
+        }
+        start_recording().await;
+        // Клавишу отпустили, пока запись запускалась
+        if STOP_REQUESTED.swap(false, Ordering::SeqCst) {
+            stop_and_paste().await;
+        }
+    });
+}
+
+fn on_release() {
+    if !IS_HELD.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    STOP_REQUESTED.store(true, Ordering::SeqCst);
+    tauri::async_runtime::spawn(async {
+        let _task = PUSH_TO_TALK_TASK.lock().await;
+        if STOP_REQUESTED.swap(false, Ordering::SeqCst) {
+            stop_and_paste().await;
+        }
+    });
+}
+
+async fn start_recording() {
+    let device_id = get_settings().device_id.unwrap_or_default();
+    if let Err(e) = record(&device_id, TranscribeOptions::default()).await {
+        eprintln!("Ошибка запуска записи: {}", e);
+    }
+}
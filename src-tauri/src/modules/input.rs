@@ -1,46 +1,162 @@
-use windows::Win32::UI::Input::KeyboardAndMouse::{
-    SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VIRTUAL_KEY,
-};
-
-pub fn paste_text(text: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let mut inputs: Vec<INPUT> = Vec::new();
-
-    for c in text.encode_utf16() {
-        // Событие нажатия клавиши
-        let input_down = INPUT {
-            r#type: INPUT_KEYBOARD,
-            Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                ki: KEYBDINPUT {
-                    wVk: VIRTUAL_KEY(0),        // Виртуальный код клавиши (0 для Unicode)
-                    wScan: c,                   // Скан-код (Unicode символ)
-                    dwFlags: KEYEVENTF_UNICODE, // Флаг для Unicode ввода
-                    time: 0,
-                    dwExtraInfo: 0,
-                },
-            },
-        };
-        inputs.push(input_down);
-
-        // Событие отпускания клавиши
-        let input_up = INPUT {
-            r#type: INPUT_KEYBOARD,
-            Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                ki: KEYBDINPUT {
-                    wVk: VIRTUAL_KEY(0),
-                    wScan: c,
-                    dwFlags: KEYEVENTF_UNICODE | KEYEVENTF_KEYUP,
-                    time: 0,
-                    dwExtraInfo: 0,
-                },
-            },
-        };
-        inputs.push(input_up);
-    }
-
-    // Отправляем события ввода
-    unsafe {
-        SendInput(&inputs, std::mem::size_of::<INPUT>() as i32); // Передаем срез и размер структуры
+mod clipboard;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use crate::modules::settings::get_settings;
+use clipboard::paste_via_clipboard;
+use inputbot::{KeybdChord, KeybdKey};
+#[cfg(target_os = "linux")]
+use linux::type_text;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+#[cfg(target_os = "windows")]
+use windows::type_text;
+
+// Пауза между действиями, чтобы окно успело обработать предыдущий ввод
+const ACTION_DELAY: Duration = Duration::from_millis(20);
+
+// Сколько символов ввела последняя диктовка. Нужно для команды "удали это"
+static LAST_INJECTED_CHARS: AtomicUsize = AtomicUsize::new(0);
+
+/// Способ ввода распознанного текста
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InjectionMode {
+    /// Посимвольная печать
+    Typing,
+    /// Вставка через буфер обмена с восстановлением его содержимого
+    Clipboard,
+}
+
+/// Действие, которое выполняется в активном окне после распознавания
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum InputAction {
+    /// Ввод текста выбранным способом
+    Text(String),
+    /// Нажатие сочетания клавиш
+    Keys(KeybdChord),
+    /// Удаление текста, введённого предыдущей диктовкой
+    DeletePrevious,
+}
+
+/// Текст, который введут действия. Из нажатий клавиш учитываются только Enter и Tab
+pub fn actions_text(actions: &[InputAction]) -> String {
+    let mut text = String::new();
+    for action in actions {
+        match action {
+            InputAction::Text(part) => text.push_str(part),
+            InputAction::Keys(chord) => text.extend(typed_char(*chord)),
+            InputAction::DeletePrevious => {}
+        }
+    }
+    text
+}
+
+/// Выполняет действия по порядку
+pub fn inject(actions: &[InputAction]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Пустая диктовка не меняет того, что удалит следующая команда "удали это"
+    if actions.is_empty() {
+        return Ok(());
+    }
+    // Счётчик обновляем и при ошибке: часть текста уже могла быть введена
+    let mut injected_chars = 0;
+    let result = run_actions(actions, &mut injected_chars);
+    LAST_INJECTED_CHARS.store(injected_chars, Ordering::SeqCst);
+    result
+}
+
+fn run_actions(
+    actions: &[InputAction],
+    injected_chars: &mut usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for (i, action) in actions.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(ACTION_DELAY);
+        }
+        match action {
+            InputAction::Text(text) => {
+                paste_text(text)?;
+                *injected_chars += text.chars().count();
+            }
+            InputAction::Keys(chord) => {
+                press_chord(*chord);
+                match typed_char(*chord) {
+                    Some(_) => *injected_chars += 1,
+                    // После других сочетаний неизвестно, какой текст остался в окне
+                    None => *injected_chars = 0,
+                }
+            }
+            InputAction::DeletePrevious => {
+                for _ in 0..LAST_INJECTED_CHARS.swap(0, Ordering::SeqCst) {
+                    KeybdKey::BackspaceKey.press();
+                    KeybdKey::BackspaceKey.release();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Символ, который вводит сочетание клавиш, если оно вводит символ
+fn typed_char(chord: KeybdChord) -> Option<char> {
+    if !chord.modifiers.is_empty() {
+        return None;
+    }
+    match chord.key {
+        KeybdKey::EnterKey => Some('\n'),
+        KeybdKey::TabKey => Some('\t'),
+        _ => None,
     }
+}
 
+// Нажимает модификаторы, затем клавишу и отпускает их в обратном порядке
+fn press_chord(chord: KeybdChord) {
+    let modifiers: Vec<KeybdKey> = chord
+        .modifiers
+        .iter()
+        .map(|modifier| modifier.keys()[0])
+        .collect();
+    for key in &modifiers {
+        key.press();
+    }
+    chord.key.press();
+    chord.key.release();
+    for key in modifiers.iter().rev() {
+        key.release();
+    }
+}
+
+// Вводит текст в активное окно выбранным способом
+fn paste_text(text: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    match get_settings().injection_mode {
+        InjectionMode::Typing => type_text(text)?,
+        InjectionMode::Clipboard => paste_via_clipboard(text)?,
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{actions_text, InputAction};
+    use inputbot::{KeybdChord, KeybdKey, Modifier};
+
+    #[test]
+    fn actions_text_keeps_text_as_typed() {
+        let actions = [
+            InputAction::DeletePrevious,
+            InputAction::Text("Готово.".to_string()),
+            InputAction::Keys(KeybdChord::new(&[], KeybdKey::EnterKey)),
+            InputAction::Text("Дальше,".to_string()),
+            InputAction::Keys(KeybdChord::new(&[Modifier::Control], KeybdKey::ZKey)),
+            InputAction::Text(" ещё".to_string()),
+        ];
+        assert_eq!(actions_text(&actions), "Готово.\nДальше, ещё");
+    }
+}
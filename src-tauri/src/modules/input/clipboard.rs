@@ -0,0 +1,61 @@
+use anyhow::Result;
+use arboard::{Clipboard, ImageData};
+use inputbot::KeybdKey;
+use std::{thread::sleep, time::Duration};
+
+// Пауза, чтобы буфер обмена успел обновиться до нажатия сочетания вставки
+const SET_DELAY: Duration = Duration::from_millis(50);
+// Пауза, чтобы приложение успело прочитать буфер обмена до его восстановления
+const RESTORE_DELAY: Duration = Duration::from_millis(200);
+
+/// Предыдущее содержимое буфера обмена
+enum PreviousContent {
+    Text(String),
+    Image(ImageData<'static>),
+    Empty,
+}
+
+/// Вставляет текст через буфер обмена и восстанавливает его прежнее содержимое
+pub fn paste_via_clipboard(text: &str) -> Result<()> {
+    let mut clipboard = Clipboard::new()?;
+    let previous = save_content(&mut clipboard);
+
+    clipboard.set_text(text)?;
+    sleep(SET_DELAY);
+    send_paste_shortcut();
+    sleep(RESTORE_DELAY);
+
+    restore_content(&mut clipboard, previous)
+}
+
+fn save_content(clipboard: &mut Clipboard) -> PreviousContent {
+    if let Ok(text) = clipboard.get_text() {
+        PreviousContent::Text(text)
+    } else if let Ok(image) = clipboard.get_image() {
+        PreviousContent::Image(image)
+    } else {
+        PreviousContent::Empty
+    }
+}
+
+fn restore_content(clipboard: &mut Clipboard, previous: PreviousContent) -> Result<()> {
+    match previous {
+        PreviousContent::Text(text) => clipboard.set_text(text)?,
+        PreviousContent::Image(image) => clipboard.set_image(image)?,
+        PreviousContent::Empty => clipboard.clear()?,
+    }
+    Ok(())
+}
+
+/// Нажимает системное сочетание вставки
+fn send_paste_shortcut() {
+    let modifier = if cfg!(target_os = "macos") {
+        KeybdKey::LSuper
+    } else {
+        KeybdKey::LControlKey
+    };
+    modifier.press();
+    KeybdKey::VKey.press();
+    KeybdKey::VKey.release();
+    modifier.release();
+}
@@ -0,0 +1,8 @@
+use anyhow::Result;
+use inputbot::KeySequence;
+
+/// Печатает текст через inputbot: в X11 через XTest, в Wayland через виртуальную клавиатуру uinput
+pub fn type_text(text: &str) -> Result<()> {
+    KeySequence(text).send();
+    Ok(())
+}
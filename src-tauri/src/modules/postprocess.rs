@@ -0,0 +1,334 @@
+pub mod voice_commands;
+
+use crate::modules::input::InputAction;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+// Звуки, которые Whisper помечает в круглых скобках или звёздочках: (applause), (soft music),
+// *laughs*. Остальные слова в скобках могут быть частью речи, поэтому не трогаем их
+const SOUND_TAGS: &[&str] = &[
+    "music",
+    "applause",
+    "laughter",
+    "laughs",
+    "laughing",
+    "silence",
+    "noise",
+    "inaudible",
+    "coughs",
+    "coughing",
+    "sighs",
+    "clapping",
+    "cheering",
+    "музыка",
+    "аплодисменты",
+    "смех",
+    "смеётся",
+    "тишина",
+    "шум",
+    "неразборчиво",
+    "кашель",
+];
+
+lazy_static! {
+    // Служебные пометки Whisper: [BLANK_AUDIO], [Music], (applause), *laughs*, ♪
+    static ref NON_SPEECH_TAGS: Regex = RegexBuilder::new(&format!(
+        r"\[[^\]]*\]|\(\s*(?:\p{{L}}+\s+)?(?:{tags})\s*\)|\*\s*(?:{tags})\s*\*|♪+",
+        tags = SOUND_TAGS.join("|")
+    ))
+    .case_insensitive(true)
+    .build()
+    .unwrap();
+    static ref SPACES: Regex = Regex::new(r"[ \t]+").unwrap();
+    static ref SPACE_BEFORE_PUNCTUATION: Regex = Regex::new(r"\s+([,.!?;:…])").unwrap();
+    static ref REPEATED_PUNCTUATION: Regex = Regex::new(r"([,;:])(?:\s*[,;:])+").unwrap();
+    // Знаки препинания, оставшиеся в начале текста или строки после удаления слов
+    static ref LEADING_PUNCTUATION: Regex = Regex::new(r"(?m)^[ \t]*[,;:]+[ \t]*").unwrap();
+    static ref SENTENCE_START: Regex = Regex::new(r"(^|[.!?…]\s+|\n\s*)(\p{Ll})").unwrap();
+    // Выражения из последних настроек. Компилируются заново, только когда настройки меняются
+    static ref FILLER_REGEX: Cache<Vec<String>, Option<Regex>> = Mutex::new(None);
+    static ref REPLACEMENT_REGEXES: Cache<Vec<Replacement>, Vec<Option<Regex>>> = Mutex::new(None);
+}
+
+// Значение вместе с ключом, по которому оно построено
+type Cache<K, V> = Mutex<Option<(K, V)>>;
+
+/// Замена по регулярному выражению, заданная пользователем
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Replacement {
+    /// Регулярное выражение в синтаксисе crate regex
+    pub pattern: String,
+    /// Текст замены, может ссылаться на группы: `$1`, `${name}`
+    pub replacement: String,
+    /// Учитывать ли регистр
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+/// Обработка распознанного текста перед вводом
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PostProcessConfig {
+    /// Убирать пробелы и переводы строк по краям и повторяющиеся пробелы
+    pub trim: bool,
+    /// Убирать пометки вроде [BLANK_AUDIO], [Music] и (applause)
+    pub strip_non_speech_tags: bool,
+    /// Начинать предложения с заглавной буквы
+    pub capitalize: bool,
+    /// Убирать пробелы перед знаками препинания и повторяющиеся запятые
+    pub fix_punctuation: bool,
+    /// Убирать слова-паразиты из `filler_words`
+    pub remove_filler_words: bool,
+    pub filler_words: Vec<String>,
+    /// Замены, применяемые по порядку
+    pub replacements: Vec<Replacement>,
+    /// Превращать произнесённые отдельно фразы вроде "новая строка" и "удали это" в знаки и действия
+    pub voice_commands: bool,
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            strip_non_speech_tags: true,
+            capitalize: false,
+            fix_punctuation: true,
+            remove_filler_words: false,
+            filler_words: [
+                "um", "uh", "uhm", "erm", "hmm", "эм", "ээ", "эээ", "мм", "хм",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            replacements: Vec::new(),
+            voice_commands: false,
+        }
+    }
+}
+
+impl PostProcessConfig {
+    /// Проверяет, что все регулярные выражения компилируются
+    pub fn validate(&self) -> Result<()> {
+        for replacement in &self.replacements {
+            compile_replacement(replacement)?;
+        }
+        Ok(())
+    }
+}
+
+/// Применяет к тексту включённые шаги обработки
+pub fn process(text: &str, config: &PostProcessConfig) -> String {
+    let mut text = text.to_string();
+
+    if config.strip_non_speech_tags {
+        text = NON_SPEECH_TAGS.replace_all(&text, "").into_owned();
+    }
+    if config.remove_filler_words {
+        if let Some(fillers) = cached(&FILLER_REGEX, &config.filler_words, |words| {
+            filler_regex(words)
+        }) {
+            text = fillers
+                .replace_all(&text, |caps: &Captures| match caps.get(1) {
+                    // В начале предложения или после другого знака запятая после слова лишняя
+                    Some(start) => start.as_str().to_string(),
+                    None => caps.get(2).map_or("", |comma| comma.as_str()).to_string(),
+                })
+                .into_owned();
+        }
+    }
+    let regexes = cached(&REPLACEMENT_REGEXES, &config.replacements, |replacements| {
+        replacements
+            .iter()
+            .map(|replacement| {
+                // Некорректные выражения отсекаются при сохранении настроек
+                compile_replacement(replacement)
+                    .inspect_err(|e| eprintln!("Пропущена замена: {}", e))
+                    .ok()
+            })
+            .collect()
+    });
+    for (replacement, regex) in config.replacements.iter().zip(regexes) {
+        if let Some(regex) = regex {
+            text = regex
+                .replace_all(&text, replacement.replacement.as_str())
+                .into_owned();
+        }
+    }
+    if config.fix_punctuation {
+        text = SPACE_BEFORE_PUNCTUATION
+            .replace_all(&text, "$1")
+            .into_owned();
+        text = REPEATED_PUNCTUATION.replace_all(&text, "$1").into_owned();
+        text = LEADING_PUNCTUATION.replace_all(&text, "").into_owned();
+    }
+    if config.trim {
+        text = SPACES.replace_all(&text, " ").into_owned();
+        text = text
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string();
+    }
+    if config.capitalize {
+        text = capitalize(&text);
+    }
+    text
+}
+
+/// Обрабатывает текст диктовки и, если включено, превращает голосовые команды в действия
+pub fn process_dictation(text: &str, config: &PostProcessConfig) -> Vec<InputAction> {
+    let text = process(text, config);
+    if !config.voice_commands {
+        if text.is_empty() {
+            return Vec::new();
+        }
+        return vec![InputAction::Text(text)];
+    }
+    voice_commands::interpret(&text)
+        .into_iter()
+        .map(|action| match action {
+            // Точки из команд начинают новые предложения
+            InputAction::Text(text) if config.capitalize => InputAction::Text(capitalize(&text)),
+            action => action,
+        })
+        .collect()
+}
+
+fn capitalize(text: &str) -> String {
+    SENTENCE_START
+        .replace_all(text, |caps: &Captures| {
+            format!("{}{}", &caps[1], caps[2].to_uppercase())
+        })
+        .into_owned()
+}
+
+// Значение из кеша, если оно построено по тому же ключу, иначе строит и запоминает новое
+fn cached<K: PartialEq + Clone, V: Clone>(
+    cache: &Cache<K, V>,
+    key: &K,
+    build: impl FnOnce(&K) -> V,
+) -> V {
+    let mut cache = cache.lock().unwrap();
+    match cache.as_ref() {
+        Some((cached_key, value)) if cached_key == key => value.clone(),
+        _ => {
+            let value = build(key);
+            *cache = Some((key.clone(), value.clone()));
+            value
+        }
+    }
+}
+
+fn compile_replacement(replacement: &Replacement) -> Result<Regex> {
+    RegexBuilder::new(&replacement.pattern)
+        .case_insensitive(!replacement.case_sensitive)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Некорректное выражение {}: {}", replacement.pattern, e))
+}
+
+// Слово-паразит целиком с пробелами перед ним и запятой после него. Первая группа - начало
+// предложения или знак препинания перед словом, вторая - запятая
+fn filler_regex(words: &[String]) -> Option<Regex> {
+    let words: Vec<String> = words
+        .iter()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty())
+        .map(regex::escape)
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    RegexBuilder::new(&format!(
+        r"(?m)(^\s*|[.!?…,;:]\s*)?[ \t]*\b(?:{})\b(,)?",
+        words.join("|")
+    ))
+    .case_insensitive(true)
+    .build()
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{process, PostProcessConfig, Replacement};
+
+    fn replacement(pattern: &str, replacement: &str) -> Replacement {
+        Replacement {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            case_sensitive: false,
+        }
+    }
+
+    #[test]
+    fn strips_non_speech_tags() {
+        let config = PostProcessConfig::default();
+        assert_eq!(
+            process("[BLANK_AUDIO] Привет (applause) мир *laughs* ♪♪", &config),
+            "Привет мир"
+        );
+        assert_eq!(process("(upbeat music) Hello", &config), "Hello");
+    }
+
+    #[test]
+    fn keeps_spoken_parentheticals() {
+        let config = PostProcessConfig::default();
+        assert_eq!(
+            process("Встреча (см. выше) завтра, *важно*", &config),
+            "Встреча (см. выше) завтра, *важно*"
+        );
+    }
+
+    #[test]
+    fn removes_filler_words_with_trailing_comma() {
+        let config = PostProcessConfig {
+            remove_filler_words: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            process("Эм, я думаю, uh, что да", &config),
+            "я думаю, что да"
+        );
+        assert_eq!(process("я думаю uh, что да", &config), "я думаю, что да");
+        assert_eq!(process("Ну. Хм, ладно", &config), "Ну. ладно");
+        // Слова, которые только начинаются как паразиты, остаются
+        assert_eq!(process("umbrella", &config), "umbrella");
+    }
+
+    #[test]
+    fn fixes_punctuation() {
+        let config = PostProcessConfig::default();
+        assert_eq!(
+            process("Привет , мир ,, как дела ?", &config),
+            "Привет, мир, как дела?"
+        );
+        assert_eq!(process(", и ещё", &config), "и ещё");
+    }
+
+    #[test]
+    fn capitalizes_sentences() {
+        let config = PostProcessConfig {
+            capitalize: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            process("первое… второе\nтретье. четвёртое", &config),
+            "Первое… Второе\nТретье. Четвёртое"
+        );
+    }
+
+    #[test]
+    fn skips_invalid_replacements() {
+        let config = PostProcessConfig {
+            replacements: vec![replacement("(", "x"), replacement("кот", "пёс")],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert_eq!(process("Кот и кот", &config), "пёс и пёс");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::modules::input::InputAction;
+use inputbot::{KeybdChord, KeybdKey, Modifier};
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+
+// Знаки, которые Whisper ставит вокруг произнесённой команды: "Hello, comma, how"
+const PUNCTUATION: &[char] = &[',', '.', '!', '?', ';', ':', '…'];
+
+/// Что делает голосовая команда
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    /// Знак препинания, присоединяется к предыдущему слову
+    Punctuation(&'static str),
+    /// Перевод строки, знаки перед командой сохраняются
+    LineBreak(&'static str),
+    /// Нажатие сочетания клавиш
+    Keys(&'static [Modifier], KeybdKey),
+    /// Удаляет последнее предложение или, если это начало диктовки, текст предыдущей диктовки
+    DeleteThat,
+}
+
+const COMMANDS: &[(&str, Command)] = &[
+    ("new paragraph", Command::LineBreak("\n\n")),
+    ("новый абзац", Command::LineBreak("\n\n")),
+    ("new line", Command::LineBreak("\n")),
+    ("новая строка", Command::LineBreak("\n")),
+    ("с новой строки", Command::LineBreak("\n")),
+    ("comma", Command::Punctuation(",")),
+    ("запятая", Command::Punctuation(",")),
+    ("period", Command::Punctuation(".")),
+    ("full stop", Command::Punctuation(".")),
+    ("точка", Command::Punctuation(".")),
+    ("question mark", Command::Punctuation("?")),
+    ("вопросительный знак", Command::Punctuation("?")),
+    ("exclamation mark", Command::Punctuation("!")),
+    ("восклицательный знак", Command::Punctuation("!")),
+    ("colon", Command::Punctuation(":")),
+    ("двоеточие", Command::Punctuation(":")),
+    ("semicolon", Command::Punctuation(";")),
+    ("точка с запятой", Command::Punctuation(";")),
+    ("press enter", Command::Keys(&[], KeybdKey::EnterKey)),
+    ("нажми ввод", Command::Keys(&[], KeybdKey::EnterKey)),
+    ("нажми энтер", Command::Keys(&[], KeybdKey::EnterKey)),
+    ("press tab", Command::Keys(&[], KeybdKey::TabKey)),
+    ("нажми таб", Command::Keys(&[], KeybdKey::TabKey)),
+    (
+        "undo that",
+        Command::Keys(&[Modifier::Control], KeybdKey::ZKey),
+    ),
+    (
+        "отмени это",
+        Command::Keys(&[Modifier::Control], KeybdKey::ZKey),
+    ),
+    ("delete that", Command::DeleteThat),
+    ("удали это", Command::DeleteThat),
+    ("удалить это", Command::DeleteThat),
+];
+
+lazy_static! {
+    static ref COMMAND_PATTERN: Regex = {
+        // Длинные фразы проверяем первыми, чтобы "точка с запятой" не стала "точкой"
+        let mut phrases: Vec<&str> = COMMANDS.iter().map(|(phrase, _)| *phrase).collect();
+        phrases.sort_by_key(|phrase| std::cmp::Reverse(phrase.chars().count()));
+        let phrases: Vec<String> = phrases
+            .into_iter()
+            .map(|phrase| regex::escape(phrase).replace(' ', r"\s+"))
+            .collect();
+        RegexBuilder::new(&format!(r"\b(?:{})\b", phrases.join("|")))
+            .case_insensitive(true)
+            .build()
+            .unwrap()
+    };
+}
+
+/// Заменяет голосовые команды в тексте знаками и действиями. Фраза считается командой, только
+/// если произнесена отдельно: до и после неё пауза (знак препинания) или край текста
+pub fn interpret(text: &str) -> Vec<InputAction> {
+    let mut actions = Vec::new();
+    // Текст, который ещё не добавлен в действия
+    let mut pending = String::new();
+    let mut rest_start = 0;
+
+    for found in COMMAND_PATTERN.find_iter(text) {
+        pending.push_str(&text[rest_start..found.start()]);
+        let command =
+            find_command(found.as_str()).filter(|_| stands_alone(text, found.start(), found.end()));
+        let Some(command) = command else {
+            pending.push_str(found.as_str());
+            rest_start = found.end();
+            continue;
+        };
+
+        let mut after = &text[found.end()..];
+        match command {
+            Command::Punctuation(mark) => {
+                trim_end_punctuation(&mut pending);
+                pending.push_str(mark);
+                after = after.trim_start_matches(PUNCTUATION);
+            }
+            Command::LineBreak(line_break) => {
+                pending.truncate(pending.trim_end().len());
+                pending.push_str(line_break);
+                after = after.trim_start_matches(PUNCTUATION).trim_start();
+            }
+            Command::Keys(modifiers, key) => {
+                pending.truncate(pending.trim_end().len());
+                flush(&mut actions, &mut pending);
+                actions.push(InputAction::Keys(KeybdChord::new(modifiers, key)));
+                after = after.trim_start_matches(PUNCTUATION).trim_start();
+            }
+            Command::DeleteThat => {
+                trim_end_punctuation(&mut pending);
+                if pending.is_empty() {
+                    if actions.is_empty() {
+                        actions.push(InputAction::DeletePrevious);
+                    }
+                } else {
+                    delete_last_sentence(&mut pending);
+                }
+                after = after.trim_start_matches(PUNCTUATION).trim_start();
+            }
+        }
+        rest_start = text.len() - after.len();
+    }
+
+    pending.push_str(&text[rest_start..]);
+    flush(&mut actions, &mut pending);
+    actions
+}
+
+fn find_command(phrase: &str) -> Option<Command> {
+    let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    COMMANDS
+        .iter()
+        .find(|(name, _)| name.to_lowercase() == phrase.to_lowercase())
+        .map(|(_, command)| *command)
+}
+
+// Обычная речь вроде "точка зрения" командой не считается
+fn stands_alone(text: &str, start: usize, end: usize) -> bool {
+    let is_pause = |c: Option<char>| match c {
+        Some(c) => c == '\n' || PUNCTUATION.contains(&c),
+        None => true,
+    };
+    is_pause(text[..start].trim_end_matches([' ', '\t']).chars().last())
+        && is_pause(text[end..].trim_start_matches([' ', '\t']).chars().next())
+}
+
+fn flush(actions: &mut Vec<InputAction>, pending: &mut String) {
+    if !pending.is_empty() {
+        actions.push(InputAction::Text(std::mem::take(pending)));
+    }
+}
+
+fn trim_end_punctuation(text: &mut String) {
+    let len = text
+        .trim_end_matches(|c: char| c.is_whitespace() || PUNCTUATION.contains(&c))
+        .len();
+    text.truncate(len);
+}
+
+// Оставляет текст до конца предыдущего предложения включительно
+fn delete_last_sentence(text: &mut String) {
+    let len = text
+        .char_indices()
+        .rev()
+        .find(|(_, c)| matches!(c, '.' | '!' | '?' | '…' | '\n'))
+        .map_or(0, |(index, c)| index + c.len_utf8());
+    text.truncate(len);
+    // Следующее предложение начнётся после пробела
+    if text.ends_with(['.', '!', '?', '…']) {
+        text.push(' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interpret;
+    use crate::modules::input::InputAction;
+    use inputbot::{KeybdChord, KeybdKey};
+
+    fn text(text: &str) -> Vec<InputAction> {
+        vec![InputAction::Text(text.to_string())]
+    }
+
+    #[test]
+    fn replaces_punctuation_between_pauses() {
+        assert_eq!(interpret("Hello, comma, how"), text("Hello, how"));
+    }
+
+    #[test]
+    fn prefers_longer_phrases() {
+        assert_eq!(interpret("Раз, точка с запятой, два"), text("Раз; два"));
+    }
+
+    #[test]
+    fn ignores_commands_inside_speech() {
+        assert_eq!(
+            interpret("Это точка зрения автора"),
+            text("Это точка зрения автора")
+        );
+        assert_eq!(
+            interpret("Press enter to continue"),
+            text("Press enter to continue")
+        );
+    }
+
+    #[test]
+    fn trims_around_line_breaks() {
+        assert_eq!(
+            interpret("Первая строка. Новая строка. Вторая"),
+            text("Первая строка.\nВторая")
+        );
+    }
+
+    #[test]
+    fn deletes_previous_dictation_at_start() {
+        assert_eq!(interpret("Delete that."), vec![InputAction::DeletePrevious]);
+    }
+
+    #[test]
+    fn deletes_last_sentence_in_the_middle() {
+        assert_eq!(
+            interpret("Первое предложение. Второе, удали это. Третье"),
+            text("Первое предложение. Третье")
+        );
+    }
+
+    #[test]
+    fn flushes_text_before_keys() {
+        assert_eq!(
+            interpret("Готово. Press enter. Дальше"),
+            vec![
+                InputAction::Text("Готово.".to_string()),
+                InputAction::Keys(KeybdChord::new(&[], KeybdKey::EnterKey)),
+                InputAction::Text("Дальше".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::app::get_app_handle;
+use crate::modules::{
+    audio::{retention::RetentionPolicy, vad::VadConfig},
+    input::InjectionMode,
+    postprocess::PostProcessConfig,
+    transcribation::{
+        _local::AUTO_LANGUAGE, model_manager::DEFAULT_MODEL, streaming::StreamingMode,
+    },
+};
+use anyhow::Result;
+use inputbot::KeybdChord;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::RwLock;
+use tauri_plugin_store::StoreExt;
+
+const STORE_FILE: &str = "settings.json";
+const STORE_KEY: &str = "settings";
+
+const MIN_RECORDING_DURATION_SECS: u64 = 1;
+const MAX_RECORDING_DURATION_SECS: u64 = 60 * 30;
+
+/// Настройки приложения, которые хранятся на стороне бэкенда
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Максимальная длительность записи в секундах
+    pub max_recording_duration_secs: u64,
+    /// Имя файла модели Whisper
+    pub model: String,
+    /// Язык распознавания или "auto"
+    pub language: String,
+    /// Языки, из которых выбирается язык при "auto". Пустой список - любой язык
+    pub language_candidates: Vec<String>,
+    /// Переводить ли распознанную речь на английский
+    pub translate: bool,
+    /// Начальная подсказка для модели, например описание темы диктовки.
+    /// Подсказка и словарь передаются локальной модели, а серверу Whisper - если он их поддерживает
+    /// (WHISPER_SEND_PROMPT)
+    pub initial_prompt: String,
+    /// Названия и термины, которые модель должна писать именно так
+    pub vocabulary: Vec<String>,
+    /// Передавать ли модели текст предыдущей диктовки как контекст
+    pub use_previous_context: bool,
+    /// Обработка распознанного текста перед вводом
+    pub post_processing: PostProcessConfig,
+    /// Способ ввода распознанного текста
+    pub injection_mode: InjectionMode,
+    /// Устройство записи по умолчанию
+    pub device_id: Option<String>,
+    /// Автоматическая остановка записи по тишине
+    pub vad: VadConfig,
+    /// Потоковое распознавание во время записи
+    pub streaming_mode: StreamingMode,
+    /// Глобальная клавиша push-to-talk в формате inputbot (например "F9" или "Ctrl+Alt+Space")
+    pub push_to_talk_key: Option<String>,
+    /// Сохранять ли записи на диск. Если нет, звук распознаётся из памяти
+    pub save_recordings: bool,
+    /// Сколько сохранённых записей хранить
+    pub recording_retention: RetentionPolicy,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_recording_duration_secs: 60 * 5,
+            model: DEFAULT_MODEL.to_string(),
+            language: AUTO_LANGUAGE.to_string(),
+            language_candidates: Vec::new(),
+            translate: false,
+            initial_prompt: String::new(),
+            vocabulary: Vec::new(),
+            use_previous_context: false,
+            post_processing: PostProcessConfig::default(),
+            injection_mode: InjectionMode::Typing,
+            device_id: None,
+            vad: VadConfig::default(),
+            streaming_mode: StreamingMode::Off,
+            push_to_talk_key: None,
+            save_recordings: true,
+            recording_retention: RetentionPolicy::KeepAll,
+        }
+    }
+}
+
+impl Settings {
+    /// Проверяет корректность значений
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_RECORDING_DURATION_SECS..=MAX_RECORDING_DURATION_SECS)
+            .contains(&self.max_recording_duration_secs)
+        {
+            return Err(anyhow::anyhow!(
+                "Длительность записи должна быть от {} до {} секунд",
+                MIN_RECORDING_DURATION_SECS,
+                MAX_RECORDING_DURATION_SECS
+            ));
+        }
+        if self.model.trim().is_empty() {
+            return Err(anyhow::anyhow!("Не выбрана модель"));
+        }
+        if let Some(key) = &self.push_to_talk_key {
+            KeybdChord::from_str(key)
+                .map_err(|e| anyhow::anyhow!("Неизвестное сочетание клавиш: {}", e))?;
+        }
+        if self.language != AUTO_LANGUAGE && !is_language_code(&self.language) {
+            return Err(anyhow::anyhow!("Некорректный язык: {}", self.language));
+        }
+        if let Some(language) = self
+            .language_candidates
+            .iter()
+            .find(|language| !is_language_code(language))
+        {
+            return Err(anyhow::anyhow!("Некорректный язык: {}", language));
+        }
+        if !(0.0..=1.0).contains(&self.vad.threshold) {
+            return Err(anyhow::anyhow!("Порог громкости должен быть от 0.0 до 1.0"));
+        }
+        if self.vad.hangover_ms == 0 {
+            return Err(anyhow::anyhow!(
+                "Длительность тишины должна быть больше нуля"
+            ));
+        }
+        self.recording_retention.validate()?;
+        self.post_processing.validate()?;
+        Ok(())
+    }
+}
+
+// Код языка Whisper: "en", "ru", "haw"
+fn is_language_code(language: &str) -> bool {
+    (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase())
+}
+
+lazy_static! {
+    static ref SETTINGS: RwLock<Settings> = RwLock::new(Settings::default());
+}
+
+/// Возвращает текущие настройки
+pub fn get_settings() -> Settings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// Загружает настройки из хранилища. Некорректные настройки заменяются значениями по умолчанию
+pub fn load_settings() -> Result<Settings> {
+    let app = get_app_handle().map_err(|e| anyhow::anyhow!(e))?;
+    let store = app.store(STORE_FILE)?;
+
+    let settings = match store.get(STORE_KEY) {
+        Some(value) => match serde_json::from_value::<Settings>(value) {
+            Ok(settings) if settings.validate().is_ok() => settings,
+            _ => {
+                eprintln!("Некорректные настройки в хранилище, используем значения по умолчанию");
+                Settings::default()
+            }
+        },
+        None => Settings::default(),
+    };
+
+    *SETTINGS.write().unwrap() = settings.clone();
+    Ok(settings)
+}
+
+/// Проверяет и сохраняет настройки
+pub fn save_settings(settings: Settings) -> Result<()> {
+    settings.validate()?;
+
+    let app = get_app_handle().map_err(|e| anyhow::anyhow!(e))?;
+    let store = app.store(STORE_FILE)?;
+    store.set(STORE_KEY, serde_json::to_value(&settings)?);
+    store.save()?;
+
+    *SETTINGS.write().unwrap() = settings;
+    Ok(())
+}
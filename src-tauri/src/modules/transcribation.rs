@@ -1,2 +1,6 @@
 pub mod _local;
+pub mod model_manager;
+pub mod prompt;
+pub mod streaming;
+pub mod transcript;
 pub mod whisper_streamer;
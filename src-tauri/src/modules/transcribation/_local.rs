@@ -1,88 +1,252 @@
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
-
-pub fn inference() -> String {
-    const PATH_TO_MODEL: &str = concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/../models/ggml-large-v3-turbo-q5_0.bin" // 547MB
-                                                  // "/../models/ggml-tiny-q5_1.bin" // 30.6MB
-                                                  // "/../models/ggml-small.bin" // 465MB
-                                                  // "/../models/ggml-large-v3-turbo.bin" // 1.51GB
-    );
-    let wav_path: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../recorded.wav");
-
-    let samples: Vec<i16> = hound::WavReader::open(wav_path)
-        .unwrap()
-        .into_samples::<i16>()
-        .map(|x| x.unwrap())
+use crate::modules::{
+    audio::{resampler::Resampler, SampleType, TARGET_SAMPLE_RATE},
+    settings::get_settings,
+    transcribation::{
+        model_manager::ModelManager,
+        prompt::build_prompt,
+        transcript::{Segment, Token, Transcript},
+    },
+};
+use anyhow::Result;
+use hound::{SampleFormat, WavReader};
+use serde::Deserialize;
+use std::sync::Arc;
+use whisper_rs::{
+    get_lang_id, get_lang_str, FullParams, SamplingStrategy, WhisperContext, WhisperState,
+};
+
+// Whisper отдаёт время сегментов в сотых долях секунды
+const MS_PER_TIMESTAMP_UNIT: i64 = 10;
+pub const AUTO_LANGUAGE: &str = "auto";
+// Определение языка не должно занимать все ядра
+const MAX_LANG_DETECT_THREADS: usize = 4;
+
+/// Параметры распознавания. Незаданные значения берутся из настроек
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TranscribeOptions {
+    /// Имя файла модели. Если не совпадает с текущей, модель загружается только для этого распознавания
+    pub model: Option<String>,
+    /// Язык распознавания или "auto"
+    pub language: Option<String>,
+    /// Языки, из которых выбирается язык при "auto". Пустой список - любой язык
+    pub language_candidates: Option<Vec<String>>,
+    /// Переводить ли распознанную речь на английский
+    pub translate: Option<bool>,
+    /// Передавать ли модели текст предыдущей диктовки как контекст
+    pub use_previous_context: Option<bool>,
+}
+
+/// Итоговые параметры модели для одного распознавания
+struct WhisperConfig {
+    language: String,
+    candidates: Vec<String>,
+    translate: bool,
+    prompt: Option<String>,
+}
+
+impl TranscribeOptions {
+    /// Подсказка для модели с учётом параметров распознавания
+    pub fn prompt(&self) -> Option<String> {
+        let settings = get_settings();
+        let use_previous_context = self
+            .use_previous_context
+            .unwrap_or(settings.use_previous_context);
+        build_prompt(&settings, use_previous_context)
+    }
+
+    fn whisper_config(&self) -> WhisperConfig {
+        let settings = get_settings();
+        WhisperConfig {
+            prompt: self.prompt(),
+            language: self.language.clone().unwrap_or(settings.language),
+            candidates: self
+                .language_candidates
+                .clone()
+                .unwrap_or(settings.language_candidates),
+            translate: self.translate.unwrap_or(settings.translate),
+        }
+    }
+}
+
+/// Распознаёт речь из WAV файла любой частоты и формата сэмплов
+pub fn inference(wav_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+    let samples = read_wav_samples(wav_path)?;
+    transcribe(&samples, options)
+}
+
+/// Распознаёт речь из сэмплов записи, минуя файл
+pub fn inference_samples(
+    samples: &[SampleType],
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    let samples: Vec<f32> = samples
+        .iter()
+        .map(|&sample| sample as f32 / SampleType::MAX as f32)
         .collect();
-    let min_samples = (1.0 * 16_000.0) as usize;
+    transcribe(&samples, options)
+}
+
+/// Распознаёт речь из сэмплов 16 кГц моно f32 и возвращает только текст
+pub fn transcribe_samples(samples: &[f32], options: &TranscribeOptions) -> Result<String> {
+    Ok(transcribe(samples, options)?.text)
+}
+
+/// Распознаёт речь из сэмплов 16 кГц моно f32
+fn transcribe(samples: &[f32], options: &TranscribeOptions) -> Result<Transcript> {
+    // Берём уже загруженную модель, чтобы не читать её с диска при каждом распознавании
+    let ctx = match options.model.as_deref() {
+        Some(model) if ModelManager::current_model().as_deref() != Some(model) => {
+            Arc::new(ModelManager::create_context(model)?)
+        }
+        _ => ModelManager::context()?,
+    };
+    run_whisper(&ctx, samples, &options.whisper_config())
+}
+
+fn run_whisper(
+    ctx: &WhisperContext,
+    samples: &[f32],
+    config: &WhisperConfig,
+) -> Result<Transcript> {
+    let min_samples = (1.0 * TARGET_SAMPLE_RATE as f32) as usize;
     if samples.len() < min_samples {
         println!("Less than 1s. Skipping...");
-        return "".to_string();
+        return Ok(Transcript::default());
     }
 
-    // load a context and model
-    let ctx = WhisperContext::new_with_params(&PATH_TO_MODEL, WhisperContextParameters::default())
-        .expect("failed to load model");
-
-    let mut state = ctx.create_state().expect("failed to create state");
+    let mut state = ctx
+        .create_state()
+        .map_err(|e| anyhow::anyhow!("Не удалось создать состояние: {}", e))?;
     let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
 
-    let language = "auto";
-    // Включаем автоматическое определение языка
-    // params.set_detect_language(true);
-    // Устанавливаем язык как auto
+    // Устанавливаем язык ("auto" - автоопределение)
+    let language = select_language(&mut state, samples, config)?;
     params.set_language(Some(&language));
-    // Явно отключаем перевод
-    params.set_translate(false);
-    // params.set_language(Some(&language, )false);
+    // Перевод на английский, если он включён
+    params.set_translate(config.translate);
+    // Словарь и контекст подсказывают модели написание терминов
+    if let Some(prompt) = &config.prompt {
+        params.set_initial_prompt(prompt);
+    }
 
-    // params.set_detect_language(true);
     // we also explicitly disable anything that prints to stdout
     params.set_print_special(false);
     params.set_print_progress(false);
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
 
-    // we must convert to 16KHz mono f32 samples for the model
-    // some utilities exist for this
-    // note that you don't need to use these, you can do it yourself or any other way you want
-    // these are just provided for convenience
-    // SIMD variants of these functions are also available, but only on nightly Rust: see the docs
-    let mut inter_samples = vec![Default::default(); samples.len()];
-
-    whisper_rs::convert_integer_to_float_audio(&samples, &mut inter_samples)
-        .expect("failed to convert audio data");
-    let samples = whisper_rs::convert_stereo_to_mono_audio(&inter_samples)
-        .expect("failed to convert audio data");
-
-    // let samples = inter_samples;
-    // now we can run the model
-    // note the key we use here is the one we created above
     state
-        .full(params, &samples[..])
-        .expect("failed to run model");
+        .full(params, samples)
+        .map_err(|e| anyhow::anyhow!("Ошибка распознавания: {}", e))?;
 
-    let mut result = String::new(); // создаём строку для накопления результатов
+    let mut segments = Vec::new();
 
-    // fetch the results [Song ends] [Singing]
     let num_segments = state
         .full_n_segments()
-        .expect("failed to get number of segments");
+        .map_err(|e| anyhow::anyhow!("Не удалось получить число сегментов: {}", e))?;
     for i in 0..num_segments {
         let segment = state
             .full_get_segment_text(i)
-            .expect("failed to get segment");
-        let start_timestamp = state
-            .full_get_segment_t0(i)
-            .expect("failed to get segment start timestamp");
-        let end_timestamp = state
-            .full_get_segment_t1(i)
-            .expect("failed to get segment end timestamp");
+            .map_err(|e| anyhow::anyhow!("Не удалось получить сегмент: {}", e))?;
+        let start_timestamp = state.full_get_segment_t0(i).unwrap_or_default();
+        let end_timestamp = state.full_get_segment_t1(i).unwrap_or_default();
         println!("[{} - {}]: {}", start_timestamp, end_timestamp, segment);
-        // Добавляем данные сегмента в строку
-        result.push_str(segment.as_str());
+        segments.push(Segment {
+            start_ms: (start_timestamp * MS_PER_TIMESTAMP_UNIT).max(0) as u64,
+            end_ms: (end_timestamp * MS_PER_TIMESTAMP_UNIT).max(0) as u64,
+            text: segment,
+            tokens: segment_tokens(ctx, &state, i)?,
+        });
+    }
+
+    // Язык, выбранный моделью. При заданном языке совпадает с ним
+    let language = state
+        .full_lang_id_from_state()
+        .ok()
+        .and_then(get_lang_str)
+        .map(String::from);
+
+    Ok(Transcript::from_segments(segments, language))
+}
+
+/// Возвращает язык для модели. При "auto" с набором кандидатов выбирает самый вероятный из них
+fn select_language(
+    state: &mut WhisperState,
+    samples: &[f32],
+    config: &WhisperConfig,
+) -> Result<String> {
+    if config.language != AUTO_LANGUAGE || config.candidates.is_empty() {
+        return Ok(config.language.clone());
+    }
+
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get().min(MAX_LANG_DETECT_THREADS))
+        .unwrap_or(1);
+    state
+        .pcm_to_mel(samples, threads)
+        .map_err(|e| anyhow::anyhow!("Не удалось подготовить аудио: {}", e))?;
+    let (_, probabilities) = state
+        .lang_detect(0, threads)
+        .map_err(|e| anyhow::anyhow!("Не удалось определить язык: {}", e))?;
+
+    config
+        .candidates
+        .iter()
+        .filter_map(|language| {
+            let id = get_lang_id(language)?;
+            Some((language, *probabilities.get(id as usize)?))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(language, _)| language.clone())
+        .ok_or_else(|| anyhow::anyhow!("Неизвестные языки: {:?}", config.candidates))
+}
+
+/// Возвращает текстовые токены сегмента с их вероятностями
+fn segment_tokens(ctx: &WhisperContext, state: &WhisperState, segment: i32) -> Result<Vec<Token>> {
+    let num_tokens = state
+        .full_n_tokens(segment)
+        .map_err(|e| anyhow::anyhow!("Не удалось получить число токенов: {}", e))?;
+
+    let mut tokens = Vec::new();
+    for i in 0..num_tokens {
+        let id = state
+            .full_get_token_id(segment, i)
+            .map_err(|e| anyhow::anyhow!("Не удалось получить токен: {}", e))?;
+        // Служебные токены (начало, конец, метки времени) идут после текстовых
+        if id >= ctx.token_eot() {
+            continue;
+        }
+        tokens.push(Token {
+            text: state
+                .full_get_token_text_lossy(segment, i)
+                .map_err(|e| anyhow::anyhow!("Не удалось получить токен: {}", e))?,
+            probability: state
+                .full_get_token_prob(segment, i)
+                .map_err(|e| anyhow::anyhow!("Не удалось получить токен: {}", e))?,
+        });
     }
+    Ok(tokens)
+}
+
+/// Читает WAV файл и приводит его к 16 кГц моно f32, как того требует модель
+fn read_wav_samples(wav_path: &str) -> Result<Vec<f32>> {
+    let mut reader = WavReader::open(wav_path)?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        SampleFormat::Int => {
+            // Нормализуем целые сэмплы любой разрядности в диапазон [-1.0, 1.0]
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
 
-    result
+    // Сводим каналы в моно и приводим частоту к той, что ожидает модель
+    let mut resampler = Resampler::new(spec.channels, spec.sample_rate, TARGET_SAMPLE_RATE);
+    Ok(resampler.process(&samples))
 }
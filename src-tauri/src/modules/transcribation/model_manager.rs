@@ -0,0 +1,114 @@
+use crate::app::get_local_data_dir;
+use crate::modules::settings::get_settings;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use whisper_rs::{WhisperContext, WhisperContextParameters};
+
+pub const DEFAULT_MODEL: &str = "ggml-large-v3-turbo-q5_0.bin"; // 547MB
+const MODELS_DIR: &str = "models";
+const MODEL_EXTENSION: &str = "bin";
+
+struct LoadedModel {
+    name: String,
+    context: Arc<WhisperContext>,
+}
+
+lazy_static! {
+    // Текущая загруженная модель, которая переиспользуется между распознаваниями
+    static ref CURRENT_MODEL: Mutex<Option<LoadedModel>> = Mutex::new(None);
+    // Не даём двум потокам одновременно загружать модели
+    static ref LOAD_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Менеджер моделей Whisper: загружает контекст один раз и держит его в памяти
+pub struct ModelManager;
+
+impl ModelManager {
+    /// Возвращает директорию с моделями в локальных данных приложения
+    pub fn models_dir() -> Result<PathBuf> {
+        let path = PathBuf::from(get_local_data_dir(MODELS_DIR)?);
+        std::fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+
+    /// Возвращает путь к файлу модели по её имени
+    pub fn model_path(name: &str) -> Result<PathBuf> {
+        // Имя модели - это только имя файла, выходить за пределы директории нельзя
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            return Err(anyhow::anyhow!("Некорректное имя модели: {}", name));
+        }
+        let path = Self::models_dir()?.join(name);
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("Модель не найдена: {}", path.display()));
+        }
+        Ok(path)
+    }
+
+    /// Получает список доступных моделей
+    pub fn list_models() -> Result<Vec<String>> {
+        let mut models: Vec<String> = std::fs::read_dir(Self::models_dir()?)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path.extension().and_then(|ext| ext.to_str()) == Some(MODEL_EXTENSION)
+            })
+            .filter_map(|path| path.file_name()?.to_str().map(String::from))
+            .collect();
+        models.sort();
+        Ok(models)
+    }
+
+    /// Загружает модель и делает её текущей. Повторная загрузка той же модели ничего не делает
+    pub fn load(name: &str) -> Result<()> {
+        let _guard = LOAD_LOCK.lock().unwrap();
+        if Self::current_model().as_deref() == Some(name) {
+            return Ok(());
+        }
+
+        let context = Self::create_context(name)?;
+        // Заменяем модель только после успешной загрузки, чтобы не остаться без модели
+        *CURRENT_MODEL.lock().unwrap() = Some(LoadedModel {
+            name: name.to_string(),
+            context: Arc::new(context),
+        });
+        println!("Модель {} загружена", name);
+        Ok(())
+    }
+
+    /// Загружает контекст модели, не делая её текущей
+    pub fn create_context(name: &str) -> Result<WhisperContext> {
+        let path = Self::model_path(name)?;
+        println!("Загрузка модели {}", path.display());
+        let path = path
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("Некорректный путь к модели"))?;
+        WhisperContext::new_with_params(path, WhisperContextParameters::default())
+            .map_err(|e| anyhow::anyhow!("Не удалось загрузить модель {}: {}", name, e))
+    }
+
+    /// Возвращает контекст текущей модели, при необходимости загружая модель из настроек
+    pub fn context() -> Result<Arc<WhisperContext>> {
+        if let Some(model) = CURRENT_MODEL.lock().unwrap().as_ref() {
+            return Ok(model.context.clone());
+        }
+        Self::load(&get_settings().model)?;
+        CURRENT_MODEL
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|model| model.context.clone())
+            .ok_or_else(|| anyhow::anyhow!("Модель не загружена"))
+    }
+
+    /// Имя текущей загруженной модели
+    pub fn current_model() -> Option<String> {
+        CURRENT_MODEL
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|model| model.name.clone())
+    }
+}
@@ -0,0 +1,133 @@
+use crate::modules::settings::Settings;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Модель учитывает только конец подсказки, длинный контекст вытесняет словарь
+const MAX_CONTEXT_CHARS: usize = 400;
+// Через сколько после последней диктовки её текст перестаёт быть контекстом
+const CONTEXT_TTL: Duration = Duration::from_secs(5 * 60);
+
+lazy_static! {
+    // Текст последней диктовки и время, когда он был получен
+    static ref PREVIOUS_TRANSCRIPT: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+}
+
+/// Запоминает текст диктовки, чтобы передать его следующей как контекст
+pub fn remember_transcript(text: &str) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    *PREVIOUS_TRANSCRIPT.lock().unwrap() = Some((text.to_string(), Instant::now()));
+}
+
+/// Собирает подсказку для Whisper из начальной подсказки, словаря и, если нужно,
+/// текста предыдущей диктовки. `None`, если подсказывать нечего
+pub fn build_prompt(settings: &Settings, use_previous_context: bool) -> Option<String> {
+    let context = if use_previous_context {
+        previous_context()
+    } else {
+        None
+    };
+    join_prompt(&settings.initial_prompt, &settings.vocabulary, context)
+}
+
+// Части идут по порядку: начальная подсказка, словарь, контекст
+fn join_prompt(
+    initial_prompt: &str,
+    vocabulary: &[String],
+    context: Option<String>,
+) -> Option<String> {
+    let mut parts = Vec::new();
+
+    let initial_prompt = initial_prompt.trim();
+    if !initial_prompt.is_empty() {
+        parts.push(initial_prompt.to_string());
+    }
+
+    let vocabulary: Vec<&str> = vocabulary
+        .iter()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if !vocabulary.is_empty() {
+        parts.push(format!("{}.", vocabulary.join(", ")));
+    }
+
+    parts.extend(context);
+
+    if parts.is_empty() {
+        return None;
+    }
+    // Whisper принимает подсказку как C-строку
+    Some(parts.join(" ").replace('\0', ""))
+}
+
+// Конец текста предыдущей диктовки, если она была недавно
+fn previous_context() -> Option<String> {
+    let previous = PREVIOUS_TRANSCRIPT.lock().unwrap();
+    let (text, time) = previous.as_ref()?;
+    if time.elapsed() > CONTEXT_TTL {
+        return None;
+    }
+    Some(clip_context(text))
+}
+
+// Последние MAX_CONTEXT_CHARS символов текста
+fn clip_context(text: &str) -> String {
+    let skip = text.chars().count().saturating_sub(MAX_CONTEXT_CHARS);
+    text.chars().skip(skip).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clip_context, join_prompt, MAX_CONTEXT_CHARS};
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn joins_prompt_vocabulary_and_context() {
+        let prompt = join_prompt(
+            " Совещание о релизе. ",
+            &words(&["Tauri", "Whisper"]),
+            Some("Вчера обсудили сроки.".to_string()),
+        );
+        assert_eq!(
+            prompt.as_deref(),
+            Some("Совещание о релизе. Tauri, Whisper. Вчера обсудили сроки.")
+        );
+    }
+
+    #[test]
+    fn skips_empty_parts() {
+        assert_eq!(join_prompt("  ", &[], None), None);
+        assert_eq!(
+            join_prompt("", &[], Some("Контекст".to_string())).as_deref(),
+            Some("Контекст")
+        );
+    }
+
+    #[test]
+    fn trims_vocabulary_words() {
+        let prompt = join_prompt("", &words(&[" Tauri ", "", "  ", "Rust"]), None);
+        assert_eq!(prompt.as_deref(), Some("Tauri, Rust."));
+    }
+
+    #[test]
+    fn removes_nul_characters() {
+        let prompt = join_prompt("a\0b", &[], None);
+        assert_eq!(prompt.as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn clips_context_to_last_chars() {
+        let short = "коротко";
+        assert_eq!(clip_context(short), short);
+
+        let text = format!("{}{}", "я".repeat(10), "ы".repeat(MAX_CONTEXT_CHARS));
+        assert_eq!(clip_context(&text), "ы".repeat(MAX_CONTEXT_CHARS));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::modules::{
+    audio::{SampleType, TARGET_SAMPLE_RATE},
+    events::record::RecordEvent,
+    postprocess,
+    settings::get_settings,
+    transcribation::{
+        _local::{transcribe_samples, TranscribeOptions},
+        whisper_streamer::WhisperStreamer,
+    },
+};
+use anyhow::Result;
+use cpal::Sample;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+
+// Как часто перезапускаем распознавание окна
+const STEP_DURATION: Duration = Duration::from_secs(1);
+// Минимальная длина окна, с которой имеет смысл распознавать
+const MIN_WINDOW_SAMPLES: usize = TARGET_SAMPLE_RATE as usize;
+// Максимальная длина окна, после которой его текст фиксируется и окно начинается заново
+const MAX_WINDOW_SAMPLES: usize = TARGET_SAMPLE_RATE as usize * 15;
+
+/// Режим потокового распознавания во время записи
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamingMode {
+    /// Текст появляется только после остановки записи
+    Off,
+    /// Локальная модель распознаёт запись скользящим окном
+    Local,
+    /// Аудио отправляется на сервер Whisper
+    Server,
+}
+
+/// Подписчик сессии, отправляющий промежуточные результаты распознавания
+pub async fn stream_transcribe(
+    rx: broadcast::Receiver<Vec<SampleType>>,
+    mode: StreamingMode,
+    options: TranscribeOptions,
+) {
+    match mode {
+        StreamingMode::Off => {}
+        StreamingMode::Local => stream_local(rx, options).await,
+        StreamingMode::Server => stream_server(rx, options).await,
+    }
+}
+
+/// Распознаёт запись локальной моделью, перезапуская распознавание окна раз в STEP_DURATION
+async fn stream_local(mut rx: broadcast::Receiver<Vec<SampleType>>, options: TranscribeOptions) {
+    // Текст уже зафиксированных окон
+    let mut committed = String::new();
+    // Сэмплы текущего окна
+    let mut window: Vec<SampleType> = Vec::new();
+    // Текущая задача распознавания и длина окна на момент её запуска
+    let mut job: Option<(JoinHandle<Result<String>>, usize)> = None;
+    let mut last_run = Instant::now();
+    // Промежуточный текст обрабатывается так же, как итоговый, кроме голосовых команд
+    let post_processing = get_settings().post_processing;
+
+    loop {
+        match rx.recv().await {
+            Ok(samples) => window.extend(samples),
+            Err(RecvError::Lagged(skipped)) => {
+                println!("Потоковое распознавание пропустило {} чанков", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        }
+
+        // Забираем результат завершившейся задачи
+        if job.as_ref().is_some_and(|(handle, _)| handle.is_finished()) {
+            let (handle, job_len) = job.take().unwrap();
+            match handle.await {
+                Ok(Ok(text)) => {
+                    let partial = if job_len >= MAX_WINDOW_SAMPLES {
+                        // Окно заполнено: фиксируем его текст и начинаем новое окно
+                        committed.push_str(&text);
+                        window.drain(..job_len);
+                        committed.clone()
+                    } else {
+                        format!("{}{}", committed, text)
+                    };
+                    RecordEvent::partial(&postprocess::process(partial.trim(), &post_processing))
+                        .send();
+                }
+                Ok(Err(e)) => eprintln!("Ошибка потокового распознавания: {}", e),
+                Err(e) => eprintln!("Ошибка задачи потокового распознавания: {}", e),
+            }
+        }
+
+        // Запускаем следующую итерацию, если предыдущая завершена
+        if job.is_none() && last_run.elapsed() >= STEP_DURATION {
+            let job_len = window.len().min(MAX_WINDOW_SAMPLES);
+            if job_len < MIN_WINDOW_SAMPLES {
+                continue;
+            }
+            let audio: Vec<f32> = window[..job_len]
+                .iter()
+                .map(|&sample| f32::from_sample(sample))
+                .collect();
+            let options = options.clone();
+            let handle = tokio::task::spawn_blocking(move || transcribe_samples(&audio, &options));
+            job = Some((handle, job_len));
+            last_run = Instant::now();
+        }
+    }
+    println!("Потоковое распознавание завершено");
+}
+
+/// Отправляет аудио на сервер Whisper. Промежуточный текст отправляет сам WhisperStreamer
+async fn stream_server(mut rx: broadcast::Receiver<Vec<SampleType>>, options: TranscribeOptions) {
+    if let Err(e) = WhisperStreamer::initialize(options.prompt()) {
+        eprintln!("Ошибка подключения к серверу Whisper: {}", e);
+        return;
+    }
+
+    loop {
+        match rx.recv().await {
+            Ok(samples) => WhisperStreamer::push_audio(samples),
+            Err(RecvError::Lagged(skipped)) => {
+                println!("Потоковое распознавание пропустило {} чанков", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+
+    // Закрытие ждёт последние строки от сервера
+    match tokio::task::spawn_blocking(WhisperStreamer::close).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => eprintln!("Ошибка закрытия соединения с сервером Whisper: {}", e),
+        Err(e) => eprintln!(
+            "Ошибка задачи закрытия соединения с сервером Whisper: {}",
+            e
+        ),
+    }
+    println!("Потоковое распознавание завершено");
+}
@@ -0,0 +1,51 @@
+use crate::modules::input::InputAction;
+use serde::Serialize;
+
+/// Результат распознавания записи
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    /// Полный текст, собранный из фрагментов
+    pub text: String,
+    /// Язык, на котором распознана речь. `None`, если распознавание не запускалось
+    pub language: Option<String>,
+    pub segments: Vec<Segment>,
+    /// Что вводится в активное окно после обработки текста. Пусто, если текст не вводится
+    pub actions: Vec<InputAction>,
+}
+
+/// Распознанный фрагмент речи
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    /// Начало фрагмента от начала записи в миллисекундах
+    pub start_ms: u64,
+    /// Конец фрагмента от начала записи в миллисекундах
+    pub end_ms: u64,
+    pub text: String,
+    /// Токены фрагмента без служебных токенов модели
+    pub tokens: Vec<Token>,
+}
+
+/// Токен модели с уверенностью распознавания
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Token {
+    pub text: String,
+    /// Вероятность токена от 0.0 до 1.0
+    pub probability: f32,
+}
+
+impl Transcript {
+    pub fn from_segments(segments: Vec<Segment>, language: Option<String>) -> Self {
+        Self {
+            text: segments
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .collect(),
+            language,
+            segments,
+            actions: Vec::new(),
+        }
+    }
+}
@@ -1,19 +1,28 @@
 use crate::app::is_debug;
 use crate::modules::errors::{ErrorCode, ErrorEmitter};
+use crate::modules::events::record::RecordEvent;
+use crate::modules::postprocess;
+use crate::modules::settings::get_settings;
 use anyhow::Result;
 use lazy_static::lazy_static;
 use std::collections::VecDeque;
 use std::env;
 use std::io::{BufRead, BufReader, Write};
-use std::net::TcpStream;
+use std::net::{Shutdown, TcpStream};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Сколько ждать, пока сервер распознает остаток аудио и закроет соединение
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(10);
 
 lazy_static! {
     static ref TCP_STREAM: Mutex<Option<TcpStream>> = Mutex::new(None);
     static ref ACCUMULATED_TEXT: Mutex<String> = Mutex::new(String::new());
     static ref SAMPLE_BUFFER: Mutex<VecDeque<Vec<SampleType>>> = Mutex::new(VecDeque::new());
+    // Поток подключения, который читает ответы сервера до конца соединения
+    static ref CONNECTION_THREAD: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
     static ref IS_PROCESSING: AtomicBool = AtomicBool::new(false);
     static ref IS_CONNECTED: AtomicBool = AtomicBool::new(false);
 }
@@ -30,8 +39,8 @@ impl WhisperStreamer {
         Ok(format!("{}:{}", host, port))
     }
 
-    /// Инициализация соединения с сервером Whisper
-    pub fn initialize() -> Result<()> {
+    /// Инициализация соединения с сервером Whisper. Подсказка отправляется, только если сервер её поддерживает
+    pub fn initialize(prompt: Option<String>) -> Result<()> {
         // Очищаем состояние
         if let Ok(mut text) = ACCUMULATED_TEXT.lock() {
             text.clear();
@@ -40,11 +49,14 @@ impl WhisperStreamer {
             buffer.clear();
         }
         IS_CONNECTED.store(false, Ordering::SeqCst);
+        // Выставляем до запуска потоков, чтобы не затереть close(), вызванный до подключения
+        IS_PROCESSING.store(true, Ordering::SeqCst);
 
         let address = Self::get_connection_string()?;
+        let prompt_line = Self::prompt_line(prompt);
 
         // Запускаем подключение в отдельном потоке
-        std::thread::spawn(move || {
+        let handle = std::thread::spawn(move || {
             match TcpStream::connect(&address) {
                 Ok(stream) => {
                     let reader_stream = match stream.try_clone() {
@@ -58,6 +70,16 @@ impl WhisperStreamer {
                         }
                     };
 
+                    // Подсказку сервер читает одной строкой до начала аудио
+                    if let Some(line) = prompt_line {
+                        if let Err(e) = (&stream).write_all(line.as_bytes()) {
+                            ErrorEmitter::emit(
+                                ErrorCode::WriteError,
+                                &format!("Failed to send prompt: {}", e),
+                            );
+                        }
+                    }
+
                     // Устанавливаем соединение
                     if let Ok(mut guard) = TCP_STREAM.lock() {
                         *guard = Some(stream);
@@ -68,13 +90,20 @@ impl WhisperStreamer {
                     Self::start_buffer_processing();
 
                     // Запускаем чтение ответов
+                    let post_processing = get_settings().post_processing;
                     let reader = BufReader::new(reader_stream);
                     for line in reader.lines() {
                         match line {
-                            Ok(text) => {
+                            Ok(line) => {
                                 if let Ok(mut accumulated) = ACCUMULATED_TEXT.lock() {
-                                    accumulated.push_str(&text);
+                                    accumulated.push_str(Self::parse_line(&line));
                                     accumulated.push('\n');
+                                    // Отправляем промежуточный текст для живых субтитров
+                                    RecordEvent::partial(&postprocess::process(
+                                        accumulated.trim(),
+                                        &post_processing,
+                                    ))
+                                    .send();
                                 }
                             }
                             Err(e) => {
@@ -95,16 +124,16 @@ impl WhisperStreamer {
                 }
             }
         });
+        *CONNECTION_THREAD.lock().unwrap() = Some(handle);
 
         Ok(())
     }
 
-    // Новый метод для обработки буфера
+    // Отправляет очередь на сервер. После close() дописывает остаток и закрывает запись,
+    // чтобы сервер распознал конец аудио и завершил соединение
     fn start_buffer_processing() {
-        IS_PROCESSING.store(true, Ordering::SeqCst);
-
         std::thread::spawn(move || {
-            while IS_PROCESSING.load(Ordering::SeqCst) {
+            loop {
                 // Получаем данные из буфера
                 let samples = {
                     let mut buffer = SAMPLE_BUFFER.lock().unwrap();
@@ -118,7 +147,7 @@ impl WhisperStreamer {
                             let raw_bytes = unsafe {
                                 std::slice::from_raw_parts(
                                     samples.as_ptr() as *const u8,
-                                    samples.len(),
+                                    std::mem::size_of_val(samples.as_slice()),
                                 )
                             };
                             if let Err(e) = stream.write_all(raw_bytes) {
@@ -130,6 +159,14 @@ impl WhisperStreamer {
                             }
                         }
                     }
+                } else if !IS_PROCESSING.load(Ordering::SeqCst) {
+                    // Буфер пуст и аудио больше не будет
+                    if let Some(stream) = TCP_STREAM.lock().unwrap().as_ref() {
+                        if let Err(e) = stream.shutdown(Shutdown::Write) {
+                            eprintln!("Ошибка закрытия отправки на сервер Whisper: {}", e);
+                        }
+                    }
+                    break;
                 } else {
                     // Если буфер пуст, делаем паузу
                     std::thread::sleep(Duration::from_millis(10));
@@ -138,6 +175,35 @@ impl WhisperStreamer {
         });
     }
 
+    /// Строка с подсказкой для сервера. Отправляется, только если сервер её поддерживает (WHISPER_SEND_PROMPT)
+    fn prompt_line(prompt: Option<String>) -> Option<String> {
+        let enabled = env::var("WHISPER_SEND_PROMPT").is_ok_and(|v| v == "1" || v == "true");
+        if !enabled {
+            return None;
+        }
+        Some(format!("{}\n", serde_json::json!({ "prompt": prompt? })))
+    }
+
+    /// Сервер присылает строки вида `<начало мс> <конец мс> <текст>`, оставляем только текст
+    fn parse_line(line: &str) -> &str {
+        let mut parts = line.trim().splitn(3, ' ');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(start), Some(end), Some(text))
+                if start.parse::<u64>().is_ok() && end.parse::<u64>().is_ok() =>
+            {
+                text
+            }
+            _ => line.trim(),
+        }
+    }
+
+    /// Добавляет аудио в очередь отправки. Данные уйдут на сервер, как только появится соединение
+    pub fn push_audio(samples: Vec<SampleType>) {
+        if let Ok(mut buffer) = SAMPLE_BUFFER.lock() {
+            buffer.push_back(samples);
+        }
+    }
+
     fn validate_audio_data(samples: &[SampleType]) -> Result<()> {
         if is_debug() {
             // Проверяем базовые параметры
@@ -146,18 +212,18 @@ impl WhisperStreamer {
             }
 
             // Проверяем размер данных
-            println!("Audio data size: {} bytes", samples.len());
+            println!("Audio data size: {} bytes", std::mem::size_of_val(samples));
 
             // Проверяем диапазон значений и считаем статистику
             let mut min_value = SampleType::MAX;
             let mut max_value = SampleType::MIN;
-            let mut sum: i32 = 0;
+            let mut sum: i64 = 0;
             let mut zero_count = 0;
 
             for &sample in samples {
                 min_value = min_value.min(sample);
                 max_value = max_value.max(sample);
-                sum += sample as i32;
+                sum += sample as i64;
                 if sample == 0 {
                     zero_count += 1;
                 }
@@ -180,7 +246,7 @@ impl WhisperStreamer {
                 println!("WARNING: All samples are zero!");
             }
 
-            if max_value - min_value < 10 {
+            if (max_value as i32 - min_value as i32) < 10 {
                 println!("WARNING: Very low dynamic range!");
             }
         }
@@ -200,11 +266,17 @@ impl WhisperStreamer {
         if let Ok(mut guard) = TCP_STREAM.lock() {
             if let Some(ref mut stream) = *guard {
                 if is_debug() {
-                    println!("Sending {} bytes to Whisper server", samples.len());
+                    println!(
+                        "Sending {} bytes to Whisper server",
+                        std::mem::size_of_val(samples)
+                    );
                 }
 
                 let raw_bytes = unsafe {
-                    std::slice::from_raw_parts(samples.as_ptr() as *const u8, samples.len())
+                    std::slice::from_raw_parts(
+                        samples.as_ptr() as *const u8,
+                        std::mem::size_of_val(samples),
+                    )
                 };
                 if let Err(e) = stream.write_all(raw_bytes) {
                     ErrorEmitter::emit(
@@ -224,9 +296,29 @@ impl WhisperStreamer {
         Ok(())
     }
 
-    /// Закрытие соединения и возврат накопленного текста
+    /// Дожидается, пока сервер распознает отправленное аудио, закрывает соединение
+    /// и возвращает накопленный текст. Блокирует поток до CLOSE_TIMEOUT
     pub fn close() -> Result<String> {
+        // Новое аудио больше не придёт: отправка допишет очередь и закроет запись
         IS_PROCESSING.store(false, Ordering::SeqCst);
+
+        // Сервер присылает последние строки и закрывает соединение
+        let connection = CONNECTION_THREAD.lock().unwrap().take();
+        if let Some(handle) = connection {
+            let deadline = Instant::now() + CLOSE_TIMEOUT;
+            while !handle.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            if handle.is_finished() {
+                let _ = handle.join();
+            } else {
+                eprintln!("Сервер Whisper не закрыл соединение вовремя");
+                if let Some(stream) = TCP_STREAM.lock().unwrap().as_ref() {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+            }
+        }
+
         IS_CONNECTED.store(false, Ordering::SeqCst);
 
         if let Ok(mut guard) = TCP_STREAM.lock() {
@@ -6,8 +6,8 @@ import { preview } from "vite";
 
 const MAX_INT_16 = 32767;
 const MAX_INT_8 = 128;
-const BITS_PER_SAMPLE: 8 | 16 = 8; // 8-bit or 16-bit audio
-const MAX_INT = BITS_PER_SAMPLE ? MAX_INT_8 : MAX_INT_16;
+const BITS_PER_SAMPLE: 8 | 16 = 16; // 8-bit or 16-bit audio
+const MAX_INT = BITS_PER_SAMPLE === 8 ? MAX_INT_8 : MAX_INT_16;
 
 export function useAudioVisualizer(options: {
   width?: number;
@@ -1,11 +1,12 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useMicrophone } from "@/composables/useMicrophone";
+import type { Transcript } from "@/types/transcript";
 // import Logger from "@/lib/system/logger";
 
 export function useTranscribe() {
   const { selected, refresh } = useMicrophone();
   const stop = async () => {
-    await invoke<string>("stop_record");
+    await invoke<Transcript>("stop_record");
     // await invoke<string>("start_transcribation");
   };
   const start = async () => {
@@ -1,9 +1,12 @@
 import type { AudioEventPayload, EventBus } from "@/types/events";
+import type { Transcript } from "@/types/transcript";
 import Logger from "@/lib/system/logger";
 
 const EVENT_AUDIO_START = "start";
 const EVENT_AUDIO_PROGRESS = "progress";
 const EVENT_AUDIO_STOP = "stop";
+const EVENT_AUDIO_PARTIAL = "partial";
+const EVENT_AUDIO_FINAL = "final";
 
 export class AudioEventService {
   constructor(private eventBus: EventBus<AudioEventPayload>) {}
@@ -28,6 +31,20 @@ export class AudioEventService {
           timestamp: event.data.timestamp,
         });
         break;
+
+      case "partial":
+        this.eventBus.emit(EVENT_AUDIO_PARTIAL, {
+          text: event.data.text,
+          timestamp: event.data.timestamp,
+        });
+        break;
+
+      case "final":
+        this.eventBus.emit(EVENT_AUDIO_FINAL, {
+          transcript: event.data.transcript,
+          timestamp: event.data.timestamp,
+        });
+        break;
     }
   }
 
@@ -47,6 +64,20 @@ export class AudioEventService {
     this.eventBus.on(EVENT_AUDIO_STOP, handler);
     return () => this.eventBus.off(EVENT_AUDIO_STOP, handler);
   }
+
+  onPartial(
+    handler: (data: { text: string; timestamp: number }) => void
+  ): () => void {
+    this.eventBus.on(EVENT_AUDIO_PARTIAL, handler);
+    return () => this.eventBus.off(EVENT_AUDIO_PARTIAL, handler);
+  }
+
+  onFinal(
+    handler: (data: { transcript: Transcript; timestamp: number }) => void
+  ): () => void {
+    this.eventBus.on(EVENT_AUDIO_FINAL, handler);
+    return () => this.eventBus.off(EVENT_AUDIO_FINAL, handler);
+  }
 }
 
 export type RecordEvent =
@@ -68,4 +99,18 @@ export type RecordEvent =
       data: {
         timestamp: number;
       };
+    }
+  | {
+      event: "partial";
+      data: {
+        timestamp: number;
+        text: string;
+      };
+    }
+  | {
+      event: "final";
+      data: {
+        timestamp: number;
+        transcript: Transcript;
+      };
     };
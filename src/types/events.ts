@@ -1,3 +1,5 @@
+import type { Transcript } from "@/types/transcript";
+
 export type EventHandler<T = any> = (payload: T) => void;
 export interface EventBus<T = any> {
   emit<K extends keyof T>(event: K, payload: T[K]): void;
@@ -10,6 +12,8 @@ export type AudioEventPayload = {
   start: { timestamp: number };
   progress: { timestamp: number; peak: number };
   stop: { timestamp: number };
+  partial: { timestamp: number; text: string };
+  final: { timestamp: number; transcript: Transcript };
 };
 
 export type BackendMessageEventPayload = {
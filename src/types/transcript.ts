@@ -0,0 +1,23 @@
+export interface TranscriptToken {
+  text: string;
+  probability: number;
+}
+
+export interface TranscriptSegment {
+  startMs: number;
+  endMs: number;
+  text: string;
+  tokens: TranscriptToken[];
+}
+
+export type InputAction =
+  | { type: "text"; value: string }
+  | { type: "keys"; value: string }
+  | { type: "deletePrevious" };
+
+export interface Transcript {
+  text: string;
+  language: string | null;
+  segments: TranscriptSegment[];
+  actions: InputAction[];
+}